@@ -0,0 +1,104 @@
+//! Attaching to a game and finding out which version it is.
+
+use alloc::format;
+use asr::{
+    future::{retry, sleep},
+    Address, Process,
+};
+use core::{fmt::Debug, time::Duration};
+
+use crate::{
+    version::{self, Probe, Unsupported},
+    MemorySource, Timer,
+};
+
+/// A process a splitter can attach to, and the module its memory is read
+/// relative to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ProcessCandidate {
+    /// The name of the process.
+    pub process: &'static str,
+    /// The name of the module holding the game's code.
+    pub module: &'static str,
+}
+
+impl ProcessCandidate {
+    /// Creates a candidate for a process whose main module has the same name.
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            process: name,
+            module: name,
+        }
+    }
+
+    /// Returns this candidate with the game's code in another module of the
+    /// process.
+    pub const fn with_module(self, module: &'static str) -> Self {
+        Self { module, ..self }
+    }
+}
+
+/// Waits until a process of one of the candidates is running and attaches to
+/// it. Candidates are tried in order, so list the most specific ones first.
+pub async fn attach_any(candidates: &[ProcessCandidate]) -> (Process, ProcessCandidate) {
+    retry(|| {
+        candidates.iter().find_map(|&candidate| {
+            Process::attach(candidate.process).map(|process| (process, candidate))
+        })
+    })
+    .await
+}
+
+/// The timer variable the detected version is published to.
+pub const VERSION_VARIABLE: &str = "Game Version";
+
+/// How long detection is retried, to give the game time to map its module.
+const DETECTION_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait between detection attempts.
+const RETRY_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Finds the base address of `module` and detects its version, retrying for a
+/// while in case the module isn't fully mapped yet.
+///
+/// The version's name, or `unknown`, is published to the
+/// [`VERSION_VARIABLE`] timer variable. If no version matches in time, the
+/// module's base address, size and identifiers are logged as a warning. The
+/// same process won't match later on either, so wait for it to close instead
+/// of attaching and detecting again right away.
+pub async fn detect_version<V, S>(
+    memory: &S,
+    module: &str,
+    versions: &[(V, &[Probe])],
+    timer: &mut impl Timer,
+) -> Option<(Address, V)>
+where
+    V: Copy + Debug,
+    S: MemorySource + ?Sized,
+{
+    let attempts = (DETECTION_TIMEOUT.as_millis() / RETRY_INTERVAL.as_millis()).max(1);
+    let mut last_range = None;
+
+    for attempt in 1..=attempts {
+        if let Some((base, size)) = memory.module_range(module) {
+            if let Some(version) = version::detect(memory, base, versions) {
+                timer.set_variable(VERSION_VARIABLE, &format!("{version:?}"));
+                return Some((base, version));
+            }
+            last_range = Some((base, size));
+        }
+        if attempt < attempts {
+            sleep(RETRY_INTERVAL).await;
+        }
+    }
+
+    timer.set_variable(VERSION_VARIABLE, "unknown");
+    match last_range {
+        Some((base, size)) => crate::warn!(
+            "{module} at 0x{:X} (module size 0x{size:X}): {}",
+            base.value(),
+            Unsupported::read(memory, base)
+        ),
+        None => crate::warn!("{module} is not loaded"),
+    }
+    None
+}
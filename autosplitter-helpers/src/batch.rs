@@ -0,0 +1,234 @@
+use alloc::vec::Vec;
+use asr::Address;
+use bytemuck::CheckedBitPattern;
+use core::mem;
+
+use crate::{modules::ModuleBases, MemoryPath, MemorySource, MemoryWatcher};
+
+/// The largest span of memory read at once for nearby watchers.
+const WINDOW: u64 = 0x1000;
+
+/// A watcher as batched updates see it: a path and the raw bytes of its value,
+/// whatever its value type.
+pub(crate) trait RawWatcher {
+    fn path(&self) -> &MemoryPath;
+    /// Returns the size of the watched value in bytes.
+    fn value_size(&self) -> usize;
+    /// Feeds the bytes of a read into the watcher, or `None` if it failed.
+    fn apply_bytes(&mut self, bytes: Option<&[u8]>);
+}
+
+impl<T: CheckedBitPattern> RawWatcher for MemoryWatcher<T> {
+    fn path(&self) -> &MemoryPath {
+        &self.path
+    }
+
+    fn value_size(&self) -> usize {
+        mem::size_of::<T>()
+    }
+
+    fn apply_bytes(&mut self, bytes: Option<&[u8]>) {
+        self.apply(bytes.and_then(|bytes| bytemuck::checked::try_pod_read_unaligned(bytes).ok()));
+    }
+}
+
+/// Updates watchers, reading the values of single-offset paths that lie within
+/// [`WINDOW`] bytes of each other with one read. Module base addresses are
+/// looked up through `modules`.
+///
+/// If a batched read fails, for example because part of the span isn't mapped,
+/// the watchers in it are read one by one instead.
+pub(crate) fn update_batched<'a, W, S>(
+    watchers: impl Iterator<Item = &'a mut W>,
+    memory: &S,
+    base: Address,
+    modules: &mut ModuleBases,
+) where
+    W: RawWatcher + ?Sized + 'a,
+    S: MemorySource + ?Sized,
+{
+    let mut buf = Vec::new();
+    let mut direct = Vec::new();
+    for watcher in watchers {
+        let path = watcher.path();
+        let base = match path.module {
+            Some(module) => modules.get(memory, module),
+            None => Some(base),
+        };
+        match (base, &*path.offsets) {
+            (Some(base), &[offset]) => direct.push(((base + offset).value(), watcher)),
+            _ => {
+                let address = base.and_then(|base| resolve(memory, base, path));
+                let bytes = address
+                    .and_then(|address| read(memory, address, watcher.value_size(), &mut buf));
+                watcher.apply_bytes(bytes);
+            }
+        }
+    }
+    direct.sort_unstable_by_key(|&(address, _)| address);
+
+    let mut rest = direct.as_mut_slice();
+    while let Some(&(start, _)) = rest.first() {
+        let len = rest
+            .iter()
+            .take_while(|(address, watcher)| {
+                address + watcher.value_size() as u64 - start <= WINDOW
+            })
+            .count()
+            .max(1);
+        let (batch, tail) = rest.split_at_mut(len);
+        rest = tail;
+
+        if let [(address, watcher)] = batch {
+            watcher.apply_bytes(read(
+                memory,
+                Address::new(*address),
+                watcher.value_size(),
+                &mut buf,
+            ));
+            continue;
+        }
+
+        let end = batch
+            .iter()
+            .map(|(address, watcher)| address + watcher.value_size() as u64)
+            .max()
+            .unwrap_or(start);
+        buf.clear();
+        buf.resize((end - start) as usize, 0);
+        if memory.read_bytes(Address::new(start), &mut buf).is_some() {
+            for (address, watcher) in batch {
+                let at = (*address - start) as usize;
+                watcher.apply_bytes(Some(&buf[at..at + watcher.value_size()]));
+            }
+        } else {
+            for (address, watcher) in batch {
+                watcher.apply_bytes(read(
+                    memory,
+                    Address::new(*address),
+                    watcher.value_size(),
+                    &mut buf,
+                ));
+            }
+        }
+    }
+}
+
+/// Follows every offset of `path` but the last from `base` and returns the
+/// address of the value.
+fn resolve<S: MemorySource + ?Sized>(
+    memory: &S,
+    base: Address,
+    path: &MemoryPath,
+) -> Option<Address> {
+    let (&last, pointers) = path.offsets.split_last()?;
+    let address = pointers.iter().try_fold(base, |address, &offset| {
+        memory.read_pointer(address + offset, path.pointer_size)
+    })?;
+    Some(address + last)
+}
+
+/// Reads `size` bytes at `address` into `buf`.
+fn read<'b, S: MemorySource + ?Sized>(
+    memory: &S,
+    address: Address,
+    size: usize,
+    buf: &'b mut Vec<u8>,
+) -> Option<&'b [u8]> {
+    buf.clear();
+    buf.resize(size, 0);
+    memory.read_bytes(address, buf)?;
+    Some(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FakeMemory, MemoryPath, MemoryWatcher, MemoryWatcherMap};
+    use alloc::{format, vec::Vec};
+    use asr::Address;
+
+    /// Compares the reads needed for 73 counters spread over a few kilobytes,
+    /// like GTA III's mission counters, one by one and batched.
+    #[test]
+    fn reads_nearby_watchers_in_one_go() {
+        let offsets = (0..73u64).map(|i| 0x35B75C + i * 0x30);
+
+        let mut memory = FakeMemory::new();
+        let base = Address::new(0x400000);
+        memory.write_bytes(0x400000u64 + 0x35B75C, &[0; 73 * 0x30]);
+        for (value, offset) in (0u32..).zip(offsets.clone()) {
+            memory.write(0x400000 + offset, value);
+        }
+
+        let mut single = offsets
+            .clone()
+            .map(MemoryWatcher::<u32>::new)
+            .collect::<Vec<_>>();
+        for watcher in &mut single {
+            watcher.update(&memory, base);
+        }
+        let single_reads = memory.reads();
+
+        let mut map = MemoryWatcherMap::<u32>::new();
+        let names = (0..73)
+            .map(|i| &*format!("mission_{i}").leak())
+            .collect::<Vec<_>>();
+        for (&name, offset) in names.iter().zip(offsets) {
+            map.insert(name, offset);
+        }
+        map.update_all(&memory, base);
+        let batched_reads = memory.reads() - single_reads;
+
+        assert_eq!(single_reads, 73);
+        assert_eq!(batched_reads, 1);
+        for (watcher, value) in single.iter().zip(0u32..) {
+            assert_eq!(watcher.pair().map(|pair| pair.current), Some(value));
+        }
+        for (i, name) in names.iter().enumerate() {
+            assert_eq!(map[name].pair().map(|pair| pair.current), Some(i as u32));
+        }
+    }
+
+    #[test]
+    fn falls_back_to_single_reads_around_unmapped_memory() {
+        let mut memory = FakeMemory::new();
+        let base = Address::new(0x1000);
+        memory.write(0x1010u64, 1u32);
+        memory.write(0x1020u64, 2u32);
+        memory.write(0x3000u64, 3u32);
+
+        let mut map = MemoryWatcherMap::<u32>::new();
+        map.insert("first", 0x10);
+        map.insert("second", 0x20);
+        map.insert("far", 0x2000);
+        map.insert("missing", 0x24);
+        map.update_all(&memory, base);
+
+        assert_eq!(map["first"].pair().map(|pair| pair.current), Some(1));
+        assert_eq!(map["second"].pair().map(|pair| pair.current), Some(2));
+        assert_eq!(map["far"].pair().map(|pair| pair.current), Some(3));
+        assert!(map["missing"].is_stale());
+    }
+
+    #[test]
+    fn reads_watchers_of_several_modules_with_cached_bases() {
+        let mut memory = FakeMemory::new();
+        memory.map_module("LaunchGTAIV.exe", 0x400000u64, 0x1000);
+        memory.map_module("GTAIV.exe", 0x800000u64, 0x1000);
+        memory.write(0x400010u64, 1u32);
+        memory.write(0x800010u64, 2u32);
+
+        let mut map = MemoryWatcherMap::<u32>::new();
+        map.insert("launcher", 0x10);
+        map.insert("game", MemoryPath::in_module("GTAIV.exe", 0x10));
+        map.insert("engine", MemoryPath::in_module("engine.dll", 0x10));
+        map.update_all(&memory, Address::new(0x400000));
+        map.update_all(&memory, Address::new(0x400000));
+
+        assert_eq!(map["launcher"].pair().map(|pair| pair.current), Some(1));
+        assert_eq!(map["game"].pair().map(|pair| pair.current), Some(2));
+        assert!(map["engine"].is_stale());
+        // The game module was looked up once, the missing one on every update.
+        assert_eq!(memory.module_lookups(), 3);
+    }
+}
@@ -0,0 +1,143 @@
+use core::time::Duration;
+
+/// The tick rate autosplitters run at unless they call
+/// [`asr::set_tick_rate`].
+pub const DEFAULT_TICK_RATE: u32 = 120;
+
+/// How long a new value has to hold before a [`DebouncedBool`] accepts it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Debounce {
+    /// A number of ticks in a row. One tick accepts every change right away.
+    Ticks(u32),
+    /// A minimum duration, rounded up to whole ticks.
+    Duration(Duration),
+}
+
+impl Debounce {
+    /// Accepts every change right away.
+    pub const OFF: Self = Self::Ticks(1);
+
+    /// Returns how many ticks in a row a new value has to hold at `tick_rate`
+    /// ticks per second.
+    pub fn ticks(self, tick_rate: u32) -> u32 {
+        let ticks = match self {
+            Self::Ticks(ticks) => ticks,
+            Self::Duration(duration) => {
+                let ticks = (duration.as_nanos() * u128::from(tick_rate)).div_ceil(1_000_000_000);
+                u32::try_from(ticks).unwrap_or(u32::MAX)
+            }
+        };
+        ticks.max(1)
+    }
+}
+
+impl Default for Debounce {
+    fn default() -> Self {
+        Self::OFF
+    }
+}
+
+/// A boolean that only changes once a new value has been seen for long
+/// enough, so one-frame flickers of a flag are ignored.
+#[derive(Clone, Debug)]
+pub struct DebouncedBool {
+    debounce: Debounce,
+    tick_rate: u32,
+    value: Option<bool>,
+    pending: u32,
+}
+
+impl DebouncedBool {
+    /// Creates a debounced boolean for a splitter running at
+    /// [`DEFAULT_TICK_RATE`].
+    pub const fn new(debounce: Debounce) -> Self {
+        Self::with_tick_rate(debounce, DEFAULT_TICK_RATE)
+    }
+
+    /// Creates a debounced boolean for a splitter running at `tick_rate` ticks
+    /// per second.
+    pub const fn with_tick_rate(debounce: Debounce, tick_rate: u32) -> Self {
+        Self {
+            debounce,
+            tick_rate,
+            value: None,
+            pending: 0,
+        }
+    }
+
+    /// Changes how long a new value has to hold, keeping the current value.
+    pub fn set_debounce(&mut self, debounce: Debounce) {
+        self.debounce = debounce;
+    }
+
+    /// Returns the accepted value, if any value was seen yet.
+    pub fn value(&self) -> Option<bool> {
+        self.value
+    }
+
+    /// Feeds this tick's raw value and returns the accepted one. The first
+    /// value is accepted right away, and `None` leaves everything as is.
+    pub fn update(&mut self, raw: Option<bool>) -> Option<bool> {
+        let Some(raw) = raw else {
+            return self.value;
+        };
+
+        match self.value {
+            Some(value) if value != raw => {
+                self.pending += 1;
+                if self.pending >= self.debounce.ticks(self.tick_rate) {
+                    self.value = Some(raw);
+                    self.pending = 0;
+                }
+            }
+            _ => {
+                self.value = Some(raw);
+                self.pending = 0;
+            }
+        }
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_changes_shorter_than_the_debounce() {
+        assert_eq!(Debounce::Duration(Duration::from_millis(50)).ticks(120), 6);
+        assert_eq!(Debounce::Duration(Duration::ZERO).ticks(120), 1);
+
+        let mut loading = DebouncedBool::new(Debounce::Ticks(3));
+        let values = [
+            Some(false),
+            Some(true),
+            Some(true),
+            None,
+            Some(false),
+            Some(true),
+            Some(true),
+            Some(true),
+            Some(false),
+        ];
+        let accepted: alloc::vec::Vec<_> = values.iter().map(|&raw| loading.update(raw)).collect();
+        assert_eq!(
+            accepted,
+            [
+                Some(false),
+                Some(false),
+                Some(false),
+                Some(false),
+                Some(false),
+                Some(false),
+                Some(false),
+                Some(true),
+                Some(true),
+            ]
+        );
+
+        let mut loading = DebouncedBool::new(Debounce::OFF);
+        assert_eq!(loading.update(Some(true)), Some(true));
+        assert_eq!(loading.update(Some(false)), Some(false));
+    }
+}
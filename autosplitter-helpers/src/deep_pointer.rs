@@ -0,0 +1,117 @@
+use asr::{Address, PointerSize};
+
+use crate::{modules::ModuleBases, MemoryPath, MemorySource};
+
+/// A pointer path shared by several watchers.
+///
+/// Every offset of the path is dereferenced, so the resolved address is the
+/// pointer found at the end of the path. Resolve it once per tick, then update
+/// the watchers that sit behind it with
+/// [`MemoryWatcher::update_from`](crate::MemoryWatcher::update_from), using
+/// paths relative to the resolved address.
+///
+/// If the path names a module, its base address is looked up once and cached
+/// until [`forget_modules`](Self::forget_modules) is called.
+pub struct DeepPointer {
+    path: MemoryPath,
+    address: Option<Address>,
+    modules: ModuleBases,
+}
+
+impl DeepPointer {
+    /// Creates an unresolved pointer for a module-relative pointer path.
+    pub fn new(path: impl Into<MemoryPath>) -> Self {
+        Self {
+            path: path.into(),
+            address: None,
+            modules: ModuleBases::new(),
+        }
+    }
+
+    /// Changes the pointer size used to dereference the path.
+    pub fn set_pointer_size(&mut self, pointer_size: PointerSize) {
+        self.path.pointer_size = pointer_size;
+    }
+
+    /// Forgets the cached module base address, for example after the game
+    /// restarted.
+    pub fn forget_modules(&mut self) {
+        self.modules.clear();
+    }
+
+    /// Follows the path from `base`, or from its module's base address if it
+    /// names one, replacing the previously resolved address.
+    pub fn resolve<S: MemorySource + ?Sized>(
+        &mut self,
+        memory: &S,
+        base: Address,
+    ) -> Option<Address> {
+        let base = match self.path.module() {
+            Some(module) => self.modules.get(memory, module),
+            None => Some(base),
+        };
+        let pointer_size = self.path.pointer_size();
+        self.address = base.and_then(|base| {
+            self.path
+                .offsets()
+                .iter()
+                .try_fold(base, |address, &offset| {
+                    memory.read_pointer(address + offset, pointer_size)
+                })
+        });
+        self.address
+    }
+
+    /// Returns the address found by the last [`resolve`](Self::resolve), if
+    /// the path could be followed.
+    pub fn address(&self) -> Option<Address> {
+        self.address
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FakeMemory, MemoryPath, MemoryWatcher};
+
+    #[test]
+    fn resolves_a_shared_base_for_child_watchers() {
+        let mut memory = FakeMemory::new();
+        let base = Address::new(0x1000);
+        memory.write_pointer(0x1010u64, 0x2000u64, PointerSize::Bit32);
+        memory.write_pointer(0x2004u64, 0x3000u64, PointerSize::Bit32);
+        memory.write(0x3000u64, 7u32);
+        memory.write(0x3008u64, 9u32);
+
+        let mut pointer = DeepPointer::new([0x10, 0x4]);
+        let mut first = MemoryWatcher::<u32>::new(0x0);
+        let mut second = MemoryWatcher::<u32>::new(0x8);
+
+        assert_eq!(pointer.resolve(&memory, base), Some(Address::new(0x3000)));
+        first.update_from(&memory, &pointer);
+        second.update_from(&memory, &pointer);
+        assert_eq!(first.pair().map(|pair| pair.current), Some(7));
+        assert_eq!(second.pair().map(|pair| pair.current), Some(9));
+
+        memory.unmap(0x2004u64, 4);
+        assert_eq!(pointer.resolve(&memory, base), None);
+        first.update_from(&memory, &pointer);
+        assert!(first.is_stale());
+    }
+
+    #[test]
+    fn resolves_paths_relative_to_their_module() {
+        let mut memory = FakeMemory::new();
+        memory.map_module("GTAIV.exe", 0x800000u64, 0x1000);
+        memory.write_pointer(0x800010u64, 0x2000u64, PointerSize::Bit32);
+
+        let mut pointer = DeepPointer::new(MemoryPath::in_module("GTAIV.exe", 0x10));
+        let base = Address::new(0x400000);
+        assert_eq!(pointer.resolve(&memory, base), Some(Address::new(0x2000)));
+        assert_eq!(pointer.resolve(&memory, base), Some(Address::new(0x2000)));
+        assert_eq!(memory.module_lookups(), 1);
+
+        let mut missing = DeepPointer::new(MemoryPath::in_module("engine.dll", 0x10));
+        assert_eq!(missing.resolve(&memory, base), None);
+    }
+}
@@ -0,0 +1,228 @@
+use alloc::{collections::VecDeque, format, string::String};
+use asr::timer::TimerState;
+use core::fmt;
+
+use crate::{Timer, TimerAction};
+
+/// How many actions a history keeps unless told otherwise, enough to explain
+/// an unexpected split.
+pub const DEFAULT_HISTORY_LENGTH: usize = 10;
+
+/// The timer variable the most recent action is published to.
+pub const LAST_ACTION_VARIABLE: &str = "Last Timer Action";
+
+/// A start, split or reset and what triggered it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub action: TimerAction,
+    pub reason: String,
+}
+
+/// The most recent starts, splits and resets of a splitter, so runners can
+/// tell which trigger caused an unexpected split.
+///
+/// Actions are recorded by driving the timer through [`ActionHistory::timer`].
+/// The most recent one is published to the [`LAST_ACTION_VARIABLE`] timer
+/// variable, and the whole history is logged at
+/// [`Level::Debug`](crate::log::Level) whenever it grows. Game time pauses
+/// aren't recorded, since they would crowd out everything else.
+pub struct ActionHistory {
+    splitter: &'static str,
+    entries: VecDeque<HistoryEntry>,
+    capacity: usize,
+}
+
+impl ActionHistory {
+    /// Creates a history of the last [`DEFAULT_HISTORY_LENGTH`] actions of
+    /// `splitter`.
+    pub fn new(splitter: &'static str) -> Self {
+        Self::with_capacity(splitter, DEFAULT_HISTORY_LENGTH)
+    }
+
+    /// Creates a history of the last `capacity` actions of `splitter`.
+    pub fn with_capacity(splitter: &'static str, capacity: usize) -> Self {
+        Self {
+            splitter,
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Returns the recorded actions, oldest first.
+    pub fn entries(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.iter()
+    }
+
+    /// Returns the most recent action.
+    pub fn last(&self) -> Option<&HistoryEntry> {
+        self.entries.back()
+    }
+
+    /// Wraps `timer` so that its starts, splits and resets are recorded.
+    pub fn timer<'a, T: Timer>(&'a mut self, timer: &'a mut T) -> HistoryTimer<'a, T> {
+        HistoryTimer {
+            history: self,
+            timer,
+        }
+    }
+
+    fn describe(&self, entry: &HistoryEntry) -> String {
+        let action = match entry.action {
+            TimerAction::Start => "start",
+            TimerAction::Split => "split",
+            TimerAction::Reset => "reset",
+            _ => "action",
+        };
+        if entry.reason.is_empty() {
+            format!("{} {action}", self.splitter)
+        } else {
+            format!("{} {action}: {}", self.splitter, entry.reason)
+        }
+    }
+
+    fn record(&mut self, action: TimerAction, reason: String, timer: &mut impl Timer) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(HistoryEntry { action, reason });
+
+        if let Some(last) = self.last() {
+            timer.set_variable(LAST_ACTION_VARIABLE, &self.describe(last));
+        }
+        crate::debug!("recent timer actions: {self}");
+    }
+}
+
+impl fmt::Display for ActionHistory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, entry) in self.entries.iter().enumerate() {
+            if index > 0 {
+                f.write_str("; ")?;
+            }
+            f.write_str(&self.describe(entry))?;
+        }
+        Ok(())
+    }
+}
+
+/// A timer whose starts, splits and resets are recorded in an
+/// [`ActionHistory`].
+pub struct HistoryTimer<'a, T> {
+    history: &'a mut ActionHistory,
+    timer: &'a mut T,
+}
+
+impl<T: Timer> Timer for HistoryTimer<'_, T> {
+    fn state(&self) -> TimerState {
+        self.timer.state()
+    }
+
+    fn current_split_index(&self) -> Option<u64> {
+        self.timer.current_split_index()
+    }
+
+    fn start(&mut self) {
+        self.timer.start();
+        self.history
+            .record(TimerAction::Start, String::new(), self.timer);
+    }
+
+    fn split(&mut self) {
+        self.timer.split();
+        self.history
+            .record(TimerAction::Split, String::new(), self.timer);
+    }
+
+    fn reset(&mut self) {
+        self.timer.reset();
+        self.history
+            .record(TimerAction::Reset, String::new(), self.timer);
+    }
+
+    fn pause_game_time(&mut self) {
+        self.timer.pause_game_time();
+    }
+
+    fn resume_game_time(&mut self) {
+        self.timer.resume_game_time();
+    }
+
+    fn set_variable(&mut self, key: &str, value: &str) {
+        self.timer.set_variable(key, value);
+    }
+
+    fn start_because(&mut self, reason: fmt::Arguments<'_>) {
+        let reason = format!("{reason}");
+        self.timer.start_because(format_args!("{reason}"));
+        self.history.record(TimerAction::Start, reason, self.timer);
+    }
+
+    fn split_because(&mut self, reason: fmt::Arguments<'_>) {
+        let reason = format!("{reason}");
+        self.timer.split_because(format_args!("{reason}"));
+        self.history.record(TimerAction::Split, reason, self.timer);
+    }
+
+    fn reset_because(&mut self, reason: fmt::Arguments<'_>) {
+        let reason = format!("{reason}");
+        self.timer.reset_because(format_args!("{reason}"));
+        self.history.record(TimerAction::Reset, reason, self.timer);
+    }
+
+    fn pause_game_time_because(&mut self, reason: fmt::Arguments<'_>) {
+        self.timer.pause_game_time_because(reason);
+    }
+
+    fn resume_game_time_because(&mut self, reason: fmt::Arguments<'_>) {
+        self.timer.resume_game_time_because(reason);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RecordingTimer;
+    use alloc::{string::ToString, vec::Vec};
+
+    #[test]
+    fn keeps_the_most_recent_actions() {
+        let mut history = ActionHistory::with_capacity("gta3", 2);
+        let mut timer = RecordingTimer::new();
+
+        let mut recorded = history.timer(&mut timer);
+        recorded.start_because(format_args!("game state 8→9"));
+        recorded.pause_game_time_because(format_args!("loading"));
+        recorded.split_because(format_args!("mission complete luigis_girls, 0→1"));
+        recorded.split();
+
+        let reasons: Vec<_> = history
+            .entries()
+            .map(|entry| entry.reason.as_str())
+            .collect();
+        assert_eq!(reasons, ["mission complete luigis_girls, 0→1", ""]);
+        assert_eq!(
+            history.to_string(),
+            "gta3 split: mission complete luigis_girls, 0→1; gta3 split"
+        );
+
+        let last_action = |value: &str| TimerAction::SetVariable {
+            key: LAST_ACTION_VARIABLE.into(),
+            value: value.into(),
+        };
+        assert_eq!(
+            timer.take_actions(),
+            [
+                TimerAction::Start,
+                last_action("gta3 start: game state 8→9"),
+                TimerAction::PauseGameTime,
+                TimerAction::Split,
+                last_action("gta3 split: mission complete luigis_girls, 0→1"),
+                TimerAction::Split,
+                last_action("gta3 split"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use alloc::boxed::Box;
+use asr::{Address, PointerSize};
+use bytemuck::CheckedBitPattern;
+use core::{marker::PhantomData, ops::Index};
+
+use crate::{
+    batch, modules::ModuleBases, MemoryPath, MemorySource, MemoryWatcher, StalePolicy,
+    WatcherLookup,
+};
+
+/// A closed set of keys identifying watchers in a [`KeyedWatchers`].
+///
+/// Use [`watcher_keys!`](crate::watcher_keys) to declare an enum implementing
+/// this trait.
+pub trait WatcherKey: Copy + 'static {
+    /// Every key, ordered by [`index`](WatcherKey::index).
+    const ALL: &'static [Self];
+
+    /// Returns the position of this key in [`ALL`](WatcherKey::ALL).
+    fn index(self) -> usize;
+
+    /// Returns the name of this key, used for settings and traces.
+    fn name(self) -> &'static str;
+}
+
+/// Declares an enum of watcher keys and implements [`WatcherKey`] for it.
+///
+/// ```
+/// autosplitter_helpers::watcher_keys! {
+///     pub enum Counter {
+///         Missions => "missions",
+///         Strongholds => "strongholds",
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! watcher_keys {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($variant:ident => $key:literal),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        $vis enum $name {
+            $($variant),*
+        }
+
+        impl $crate::WatcherKey for $name {
+            const ALL: &'static [Self] = &[$(Self::$variant),*];
+
+            fn index(self) -> usize {
+                self as usize
+            }
+
+            fn name(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $key),*
+                }
+            }
+        }
+    };
+}
+
+/// Watchers of the same value type stored densely, one for every key of `K`.
+///
+/// Unlike [`MemoryWatcherMap`](crate::MemoryWatcherMap), every key is
+/// guaranteed to have a watcher, so indexing can't fail on a misspelled name.
+/// Module base addresses are cached like in a `MemoryWatcherMap`.
+pub struct KeyedWatchers<K, T> {
+    watchers: Box<[MemoryWatcher<T>]>,
+    modules: ModuleBases,
+    keys: PhantomData<K>,
+}
+
+impl<K: WatcherKey, T> KeyedWatchers<K, T> {
+    /// Creates a watcher for every key, using `path` to look up its path.
+    pub fn new<P: Into<MemoryPath>>(mut path: impl FnMut(K) -> P) -> Self {
+        debug_assert!(K::ALL.iter().enumerate().all(|(i, key)| key.index() == i));
+        Self {
+            watchers: K::ALL
+                .iter()
+                .map(|&key| MemoryWatcher::new(path(key)))
+                .collect(),
+            modules: ModuleBases::new(),
+            keys: PhantomData,
+        }
+    }
+
+    /// Iterates over the watchers in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (K, &MemoryWatcher<T>)> {
+        K::ALL.iter().copied().zip(self.watchers.iter())
+    }
+
+    /// Returns the watcher for `key`, for feeding it values directly.
+    pub(crate) fn get_mut(&mut self, key: K) -> &mut MemoryWatcher<T> {
+        &mut self.watchers[key.index()]
+    }
+
+    /// Changes the pointer size of every watcher.
+    pub fn set_pointer_size(&mut self, pointer_size: PointerSize) {
+        for watcher in self.watchers.iter_mut() {
+            watcher.set_pointer_size(pointer_size);
+        }
+    }
+
+    /// Changes the stale policy of every watcher.
+    pub fn set_stale_policy(&mut self, policy: StalePolicy) {
+        for watcher in self.watchers.iter_mut() {
+            watcher.set_stale_policy(policy);
+        }
+    }
+
+    /// Forgets the cached module base addresses. Call this when attaching to
+    /// a new process if the watchers outlive the old one.
+    pub fn forget_modules(&mut self) {
+        self.modules.clear();
+    }
+}
+
+impl<K: WatcherKey, T: CheckedBitPattern> KeyedWatchers<K, T> {
+    /// Updates every watcher. Watchers at nearby offsets are read together.
+    pub fn update_all<S: MemorySource + ?Sized>(&mut self, memory: &S, base: Address) {
+        batch::update_batched(self.watchers.iter_mut(), memory, base, &mut self.modules);
+    }
+}
+
+impl<K: WatcherKey, T> Index<K> for KeyedWatchers<K, T> {
+    type Output = MemoryWatcher<T>;
+
+    fn index(&self, key: K) -> &Self::Output {
+        &self.watchers[key.index()]
+    }
+}
+
+impl<K: WatcherKey, T> WatcherLookup<K, T> for KeyedWatchers<K, T> {
+    fn watcher(&self, key: K) -> Option<&MemoryWatcher<T>> {
+        self.watchers.get(key.index())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FakeMemory;
+
+    crate::watcher_keys! {
+        enum Counter {
+            Missions => "missions",
+            FightClub => "fight_club",
+        }
+    }
+
+    #[test]
+    fn stores_one_watcher_per_key() {
+        let mut memory = FakeMemory::new();
+        let mut counters = KeyedWatchers::<Counter, u32>::new(|key| match key {
+            Counter::Missions => 0x10,
+            Counter::FightClub => 0x14,
+        });
+
+        for value in [1u32, 2] {
+            memory.write(0x1010u64, value);
+            memory.write(0x1014u64, 7u32);
+            counters.update_all(&memory, Address::new(0x1000));
+        }
+
+        assert!(counters[Counter::Missions].increased_by(1));
+        assert!(!counters[Counter::FightClub].increased_by(1));
+        assert_eq!(
+            counters
+                .iter()
+                .map(|(key, _)| key.name())
+                .collect::<alloc::vec::Vec<_>>(),
+            ["missions", "fight_club"]
+        );
+    }
+}
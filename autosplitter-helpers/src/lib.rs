@@ -2,45 +2,158 @@
 
 extern crate alloc;
 
+pub mod attach;
+mod batch;
+mod debounce;
+mod deep_pointer;
+mod history;
+mod keyed;
+mod load_remover;
+pub mod log;
+mod modules;
+pub mod pe;
+mod registry;
+mod route;
+pub mod rules;
+#[cfg(feature = "derive")]
+pub mod settings;
+pub mod sigscan;
+mod source;
+mod split_guard;
+mod timer;
+pub mod trace;
+mod variables;
+pub mod version;
+
+pub use debounce::{Debounce, DebouncedBool, DEFAULT_TICK_RATE};
+pub use deep_pointer::DeepPointer;
+pub use history::{
+    ActionHistory, HistoryEntry, HistoryTimer, DEFAULT_HISTORY_LENGTH, LAST_ACTION_VARIABLE,
+};
+pub use keyed::{KeyedWatchers, WatcherKey};
+pub use load_remover::LoadRemover;
+use modules::ModuleBases;
+pub use registry::{RegistryKey, WatcherRegistry};
+pub use route::{Route, RouteCheck};
+pub use source::{FakeMemory, MemorySource};
+pub use split_guard::SplitGuard;
+pub use timer::{AsrTimer, RecordingTimer, Timer, TimerAction};
+pub use variables::Variables;
+
 use alloc::{boxed::Box, collections::BTreeMap};
 use asr::{
+    string::{ArrayCString, ArrayWString},
     watcher::{Pair, Watcher},
-    Address, PointerSize, Process,
+    Address, PointerSize,
 };
 use bytemuck::CheckedBitPattern;
 use core::ops::Index;
 
 /// An owned pointer path relative to a module's base address.
-pub struct MemoryPath(Box<[u64]>);
+///
+/// Paths created through the `From` conversions are read with 32-bit pointers
+/// from the base address passed to the watcher's update. Use
+/// [`MemoryPath::bit64`] or [`MemoryPath::with_pointer_size`] for 64-bit games
+/// and [`MemoryPath::in_module`] for values in another module.
+pub struct MemoryPath {
+    offsets: Box<[u64]>,
+    pointer_size: PointerSize,
+    module: Option<&'static str>,
+}
+
+impl MemoryPath {
+    /// Creates a path relative to the base address of the named module
+    /// instead of the base address passed to the update.
+    pub fn in_module(module: &'static str, path: impl Into<MemoryPath>) -> Self {
+        Self {
+            module: Some(module),
+            ..path.into()
+        }
+    }
+
+    /// Creates a path that is dereferenced with 64-bit pointers.
+    pub fn bit64(path: impl Into<MemoryPath>) -> Self {
+        path.into().with_pointer_size(PointerSize::Bit64)
+    }
+
+    /// Returns this path dereferenced with the given pointer size.
+    pub fn with_pointer_size(mut self, pointer_size: PointerSize) -> Self {
+        self.pointer_size = pointer_size;
+        self
+    }
+
+    /// Returns the offsets that make up this path.
+    pub fn offsets(&self) -> &[u64] {
+        &self.offsets
+    }
+
+    /// Returns the pointer size used to dereference this path.
+    pub fn pointer_size(&self) -> PointerSize {
+        self.pointer_size
+    }
+
+    /// Returns the module this path is relative to, if it isn't relative to
+    /// the base address passed to the update.
+    pub fn module(&self) -> Option<&'static str> {
+        self.module
+    }
+}
 
 impl From<u64> for MemoryPath {
     fn from(offset: u64) -> Self {
-        Self(Box::new([offset]))
+        Box::<[u64]>::from([offset]).into()
     }
 }
 
 impl<const N: usize> From<[u64; N]> for MemoryPath {
     fn from(path: [u64; N]) -> Self {
-        Self(Box::new(path))
+        Box::<[u64]>::from(path).into()
     }
 }
 
 impl From<&[u64]> for MemoryPath {
     fn from(path: &[u64]) -> Self {
-        Self(path.into())
+        Box::<[u64]>::from(path).into()
     }
 }
 
 impl From<Box<[u64]>> for MemoryPath {
-    fn from(path: Box<[u64]>) -> Self {
-        Self(path)
+    fn from(offsets: Box<[u64]>) -> Self {
+        Self {
+            offsets,
+            pointer_size: PointerSize::Bit32,
+            module: None,
+        }
     }
 }
 
+/// What a watcher does with its value pair when a read fails.
+///
+/// With every policy the pair stops reporting a transition while reads fail,
+/// so a predicate can't keep firing on the last change during a read gap. By
+/// default no transition is reported across the gap either.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum StalePolicy {
+    /// Keeps the last value. The first successful read after the gap is
+    /// compared against it, so a change across the gap is reported.
+    Hold,
+    /// Drops the pair, so predicates see no value during the gap and the
+    /// first successful read afterwards starts a new pair.
+    Invalidate,
+    /// Keeps the last value during the gap, but treats the first successful
+    /// read afterwards as unchanged.
+    #[default]
+    SuppressNextTransition,
+}
+
 /// An ASR watcher paired with the pointer path used to update it.
 pub struct MemoryWatcher<T> {
     watcher: Watcher<T>,
     path: MemoryPath,
+    policy: StalePolicy,
+    updates: u64,
+    failures: u32,
+    last_success: Option<u64>,
 }
 
 impl<T> MemoryWatcher<T> {
@@ -49,6 +162,10 @@ impl<T> MemoryWatcher<T> {
         Self {
             watcher: Watcher::new(),
             path: path.into(),
+            policy: StalePolicy::SuppressNextTransition,
+            updates: 0,
+            failures: 0,
+            last_success: None,
         }
     }
 
@@ -56,20 +173,138 @@ impl<T> MemoryWatcher<T> {
     pub fn pair(&self) -> Option<&Pair<T>> {
         self.watcher.pair.as_ref()
     }
+
+    /// Changes the pointer size used to dereference this watcher's path.
+    pub fn set_pointer_size(&mut self, pointer_size: PointerSize) {
+        self.path.pointer_size = pointer_size;
+    }
+
+    /// Changes what the watcher does when a read fails.
+    pub fn set_stale_policy(&mut self, policy: StalePolicy) {
+        self.policy = policy;
+    }
+
+    /// Returns whether the most recent read failed.
+    pub fn is_stale(&self) -> bool {
+        self.failures > 0
+    }
+
+    /// Returns the number of reads that failed in a row, up to the most
+    /// recent one.
+    pub fn consecutive_failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Returns the update during which the last successful read happened,
+    /// counting this watcher's updates from 1.
+    pub fn last_success_tick(&self) -> Option<u64> {
+        self.last_success
+    }
+}
+
+impl<T: Copy> MemoryWatcher<T> {
+    /// Feeds the result of a read into the watcher, applying its stale policy.
+    fn apply(&mut self, value: Option<T>) {
+        self.updates += 1;
+
+        let Some(value) = value else {
+            self.failures = self.failures.saturating_add(1);
+            match self.policy {
+                StalePolicy::Invalidate => self.watcher.pair = None,
+                StalePolicy::Hold | StalePolicy::SuppressNextTransition => {
+                    if let Some(pair) = &mut self.watcher.pair {
+                        pair.old = pair.current;
+                    }
+                }
+            }
+            return;
+        };
+
+        let recovered = self.failures > 0;
+        self.failures = 0;
+        self.last_success = Some(self.updates);
+
+        if recovered && self.policy == StalePolicy::SuppressNextTransition {
+            self.watcher.pair = Some(Pair {
+                old: value,
+                current: value,
+            });
+        } else {
+            self.watcher.update_infallible(value);
+        }
+    }
 }
 
 impl<T: CheckedBitPattern> MemoryWatcher<T> {
-    /// Reads the value at this watcher's path from a 32-bit process.
-    pub fn update(&mut self, process: &Process, base: Address) {
-        self.watcher.update(
-            process
-                .read_pointer_path(base, PointerSize::Bit32, &self.path.0)
-                .ok(),
-        );
+    /// Reads the value at this watcher's path.
+    ///
+    /// If the path names a module, it is read relative to that module's base
+    /// address, which is looked up on every call. Put such watchers in a
+    /// [`MemoryWatcherMap`] to look it up only once.
+    pub fn update<S: MemorySource + ?Sized>(&mut self, memory: &S, base: Address) {
+        let base = match self.path.module {
+            Some(module) => memory.module_address(module),
+            None => Some(base),
+        };
+        self.read_from(memory, base);
+    }
+
+    /// Reads the value at this watcher's path, relative to the address a
+    /// [`DeepPointer`] resolved to this tick. The read fails if it didn't
+    /// resolve.
+    pub fn update_from<S: MemorySource + ?Sized>(&mut self, memory: &S, pointer: &DeepPointer) {
+        self.read_from(memory, pointer.address());
+    }
+
+    /// Reads the value at this watcher's path relative to `base`, failing if
+    /// there is no base.
+    fn read_from<S: MemorySource + ?Sized>(&mut self, memory: &S, base: Option<Address>) {
+        let value = base.and_then(|base| {
+            memory.read_pointer_path(base, self.path.pointer_size, &self.path.offsets)
+        });
+        self.apply(value);
     }
 }
 
-macro_rules! impl_increased_by {
+impl<T: PartialEq> MemoryWatcher<T> {
+    /// Returns whether the value changed.
+    pub fn changed(&self) -> bool {
+        self.pair().is_some_and(|pair| pair.old != pair.current)
+    }
+
+    /// Returns whether the value changed to `value`.
+    pub fn changed_to(&self, value: &T) -> bool {
+        self.pair()
+            .is_some_and(|pair| pair.old != *value && pair.current == *value)
+    }
+
+    /// Returns whether the value changed from `old` to `current`.
+    pub fn changed_from_to(&self, old: &T, current: &T) -> bool {
+        self.pair()
+            .is_some_and(|pair| pair.old == *old && pair.current == *current)
+    }
+}
+
+impl<const N: usize> MemoryWatcher<ArrayCString<N>> {
+    /// Returns whether the string changed to `text`.
+    pub fn changed_to_str(&self, text: &str) -> bool {
+        self.pair().is_some_and(|pair| {
+            pair.old.validate_utf8() != Ok(text) && pair.current.validate_utf8() == Ok(text)
+        })
+    }
+}
+
+impl<const N: usize> MemoryWatcher<ArrayWString<N>> {
+    /// Returns whether the string changed to `text`.
+    pub fn changed_to_str(&self, text: &str) -> bool {
+        let matches =
+            |string: &ArrayWString<N>| string.as_slice().iter().copied().eq(text.encode_utf16());
+        self.pair()
+            .is_some_and(|pair| !matches(&pair.old) && matches(&pair.current))
+    }
+}
+
+macro_rules! impl_integer_predicates {
     ($($ty:ty),* $(,)?) => {
         $(
             impl MemoryWatcher<$ty> {
@@ -81,16 +316,70 @@ macro_rules! impl_increased_by {
                             .is_some_and(|expected| pair.current == expected)
                     })
                 }
+
+                /// Returns whether the value decreased by exactly `amount`.
+                pub fn decreased_by(&self, amount: $ty) -> bool {
+                    self.pair().is_some_and(|pair| {
+                        pair.old
+                            .checked_sub(amount)
+                            .is_some_and(|expected| pair.current == expected)
+                    })
+                }
+
+                /// Returns whether the value moved across `threshold` in either
+                /// direction, i.e. it was below it before and isn't now, or the
+                /// other way around.
+                pub fn crossed_threshold(&self, threshold: $ty) -> bool {
+                    self.pair()
+                        .is_some_and(|pair| (pair.old < threshold) != (pair.current < threshold))
+                }
+
+                /// Returns whether the value went from below `max` to at least
+                /// `max`.
+                pub fn reached(&self, max: $ty) -> bool {
+                    self.pair()
+                        .is_some_and(|pair| pair.old < max && pair.current >= max)
+                }
+
+                /// Returns whether the value changed from zero to anything else.
+                pub fn became_nonzero(&self) -> bool {
+                    self.pair()
+                        .is_some_and(|pair| pair.old == 0 && pair.current != 0)
+                }
+
+                /// Returns whether bit number `bit` changed from 0 to 1.
+                pub fn bit_set(&self, bit: u32) -> bool {
+                    let mask = (1 as $ty).checked_shl(bit).unwrap_or(0);
+                    self.pair()
+                        .is_some_and(|pair| pair.old & mask == 0 && pair.current & mask != 0)
+                }
+
+                /// Returns whether bit number `bit` changed from 1 to 0.
+                pub fn bit_cleared(&self, bit: u32) -> bool {
+                    let mask = (1 as $ty).checked_shl(bit).unwrap_or(0);
+                    self.pair()
+                        .is_some_and(|pair| pair.old & mask != 0 && pair.current & mask == 0)
+                }
             }
         )*
     };
 }
 
-impl_increased_by!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+impl_integer_predicates!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+/// Looks up watchers of the same value type by key.
+pub trait WatcherLookup<K, T> {
+    /// Returns the watcher registered under `key`.
+    fn watcher(&self, key: K) -> Option<&MemoryWatcher<T>>;
+}
 
 /// A named collection of memory watchers of the same value type.
+///
+/// Base addresses of the modules named by the watchers' paths are looked up
+/// once and cached until [`forget_modules`](Self::forget_modules) is called.
 pub struct MemoryWatcherMap<T> {
     watchers: BTreeMap<&'static str, MemoryWatcher<T>>,
+    modules: ModuleBases,
 }
 
 impl<T> MemoryWatcherMap<T> {
@@ -98,6 +387,7 @@ impl<T> MemoryWatcherMap<T> {
     pub const fn new() -> Self {
         Self {
             watchers: BTreeMap::new(),
+            modules: ModuleBases::new(),
         }
     }
 
@@ -115,15 +405,34 @@ impl<T> MemoryWatcherMap<T> {
     pub fn iter(&self) -> impl Iterator<Item = (&'static str, &MemoryWatcher<T>)> {
         self.watchers.iter().map(|(&name, watcher)| (name, watcher))
     }
-}
 
-impl<T: CheckedBitPattern> MemoryWatcherMap<T> {
-    /// Updates every watcher in the map.
-    pub fn update_all(&mut self, process: &Process, base: Address) {
+    /// Changes the pointer size of every watcher currently in the map.
+    pub fn set_pointer_size(&mut self, pointer_size: PointerSize) {
+        for watcher in self.watchers.values_mut() {
+            watcher.set_pointer_size(pointer_size);
+        }
+    }
+
+    /// Changes the stale policy of every watcher currently in the map.
+    pub fn set_stale_policy(&mut self, policy: StalePolicy) {
         for watcher in self.watchers.values_mut() {
-            watcher.update(process, base);
+            watcher.set_stale_policy(policy);
         }
     }
+
+    /// Forgets the cached module base addresses. Call this when attaching to
+    /// a new process if the map outlives the old one.
+    pub fn forget_modules(&mut self) {
+        self.modules.clear();
+    }
+}
+
+impl<T: CheckedBitPattern> MemoryWatcherMap<T> {
+    /// Updates every watcher in the map. Watchers at nearby offsets are read
+    /// together.
+    pub fn update_all<S: MemorySource + ?Sized>(&mut self, memory: &S, base: Address) {
+        batch::update_batched(self.watchers.values_mut(), memory, base, &mut self.modules);
+    }
 }
 
 impl<T> Default for MemoryWatcherMap<T> {
@@ -132,6 +441,12 @@ impl<T> Default for MemoryWatcherMap<T> {
     }
 }
 
+impl<T> WatcherLookup<&str, T> for MemoryWatcherMap<T> {
+    fn watcher(&self, name: &str) -> Option<&MemoryWatcher<T>> {
+        self.get(name)
+    }
+}
+
 impl<T> Index<&str> for MemoryWatcherMap<T> {
     type Output = MemoryWatcher<T>;
 
@@ -164,17 +479,136 @@ mod tests {
         let _ = &watchers["missions"];
     }
 
+    #[test]
+    fn updates_every_watcher_in_a_map_from_one_source() {
+        let mut memory = FakeMemory::new();
+        memory.write(0x1010u64, 1u32);
+        memory.write_pointer(0x1020u64, 0x2000u64, PointerSize::Bit32);
+        memory.write(0x2008u64, 7u32);
+
+        let mut watchers = MemoryWatcherMap::<u32>::new();
+        watchers.insert("direct", 0x10);
+        watchers.insert("pointer", [0x20, 0x8]);
+        watchers.update_all(&memory, Address::new(0x1000));
+
+        memory.write(0x1010u64, 2u32);
+        watchers.update_all(&memory, Address::new(0x1000));
+
+        assert!(watchers["direct"].increased_by(1));
+        assert_eq!(watchers["pointer"].pair().map(|pair| pair.current), Some(7));
+    }
+
+    #[test]
+    fn detects_transitions_of_integers_strings_and_bools() {
+        let mut memory = FakeMemory::new();
+        let base = Address::new(0x1000);
+        let mut state = MemoryWatcher::<u32>::new(0x10);
+        let mut flag = MemoryWatcher::<bool>::new(0x14);
+        let mut name = MemoryWatcher::<ArrayCString<8>>::new(0x18);
+
+        memory.write(0x1010u64, 0b0101u32);
+        memory.write(0x1014u64, 0u8);
+        memory.write_bytes(0x1018u64, b"intro\0\0\0");
+        state.update(&memory, base);
+        flag.update(&memory, base);
+        name.update(&memory, base);
+
+        memory.write(0x1010u64, 0b0110u32);
+        memory.write(0x1014u64, 1u8);
+        memory.write_bytes(0x1018u64, b"outro\0\0\0");
+        state.update(&memory, base);
+        flag.update(&memory, base);
+        name.update(&memory, base);
+
+        assert!(state.changed());
+        assert!(state.changed_from_to(&5, &6));
+        assert!(state.increased_by(1));
+        assert!(!state.decreased_by(1));
+        assert!(state.crossed_threshold(6));
+        assert!(state.reached(6));
+        assert!(!state.reached(5));
+        assert!(!state.became_nonzero());
+        assert!(state.bit_set(1));
+        assert!(state.bit_cleared(0));
+        assert!(!state.bit_set(2));
+        assert!(!state.bit_set(40));
+        assert!(flag.changed_to(&true));
+        assert!(name.changed_to_str("outro"));
+        assert!(!name.changed_to_str("intro"));
+    }
+
+    #[test]
+    fn never_reports_a_transition_across_a_read_gap_unless_holding() {
+        let base = Address::new(0x1000);
+        let run = |policy| {
+            let mut memory = FakeMemory::new();
+            let mut watcher = MemoryWatcher::<u32>::new(0x10);
+            watcher.set_stale_policy(policy);
+            let mut increases = alloc::vec::Vec::new();
+
+            for value in [Some(1u32), Some(2), None, None, Some(3), Some(3)] {
+                match value {
+                    Some(value) => memory.write(0x1010u64, value),
+                    None => memory.unmap(0x1010u64, 4),
+                }
+                watcher.update(&memory, base);
+                increases.push(watcher.increased_by(1));
+            }
+            (increases, watcher)
+        };
+
+        let (increases, watcher) = run(StalePolicy::Hold);
+        assert_eq!(increases, [false, true, false, false, true, false]);
+        assert!(!watcher.is_stale());
+        assert_eq!(watcher.last_success_tick(), Some(6));
+
+        let (increases, _) = run(StalePolicy::Invalidate);
+        assert_eq!(increases, [false, true, false, false, false, false]);
+
+        let (increases, _) = run(StalePolicy::SuppressNextTransition);
+        assert_eq!(increases, [false, true, false, false, false, false]);
+
+        let mut watcher = MemoryWatcher::<u32>::new(0x10);
+        watcher.update(&FakeMemory::new(), base);
+        watcher.update(&FakeMemory::new(), base);
+        assert!(watcher.is_stale());
+        assert_eq!(watcher.consecutive_failures(), 2);
+        assert_eq!(watcher.last_success_tick(), None);
+    }
+
+    #[test]
+    fn paths_default_to_32_bit_pointers() {
+        let path = MemoryPath::from([0x10, 0x4]);
+        assert_eq!(path.offsets(), [0x10, 0x4]);
+        assert_eq!(path.pointer_size(), PointerSize::Bit32);
+
+        let path = MemoryPath::bit64(0x10);
+        assert_eq!(path.offsets(), [0x10]);
+        assert_eq!(path.pointer_size(), PointerSize::Bit64);
+
+        let mut watchers = MemoryWatcherMap::<u32>::new();
+        watchers.insert("missions", 0x1234);
+        watchers.set_pointer_size(PointerSize::Bit64);
+        assert_eq!(watchers["missions"].path.pointer_size(), PointerSize::Bit64);
+    }
+
     #[test]
     fn detects_exact_increases_without_overflowing() {
-        let mut watcher = MemoryWatcher::<u8>::new(0);
+        let mut memory = FakeMemory::new();
+        let mut watcher = MemoryWatcher::<u8>::new(0x10);
+        let base = Address::new(0x1000);
 
-        watcher.watcher.update_infallible(10);
-        watcher.watcher.update_infallible(11);
+        for value in [10, 11] {
+            memory.write(0x1010u64, value);
+            watcher.update(&memory, base);
+        }
         assert!(watcher.increased_by(1));
         assert!(!watcher.increased_by(2));
 
-        watcher.watcher.update_infallible(u8::MAX);
-        watcher.watcher.update_infallible(0);
+        for value in [u8::MAX, 0] {
+            memory.write(0x1010u64, value);
+            watcher.update(&memory, base);
+        }
         assert!(!watcher.increased_by(1));
     }
 }
@@ -0,0 +1,170 @@
+use alloc::{boxed::Box, vec::Vec};
+use asr::timer::TimerState;
+
+use crate::{Debounce, DebouncedBool, Timer};
+
+/// A check that tells whether the game is loading, or `None` if it can't
+/// tell, for example because a watcher has no value yet.
+type LoadingPredicate<W> = Box<dyn Fn(&W) -> Option<bool>>;
+
+/// Pauses game time while the game is loading.
+///
+/// The game counts as loading while any of its predicates says so, and as not
+/// loading once all of them say it isn't. Game time is only paused or resumed
+/// when that changes, or when the timer state changes, since LiveSplit resets
+/// the pause state on its own when a run starts or resets.
+///
+/// A [`Debounce`] can be set to ignore loading flags that flicker for a
+/// moment.
+pub struct LoadRemover<W> {
+    predicates: Vec<LoadingPredicate<W>>,
+    loading: DebouncedBool,
+    paused: Option<bool>,
+    timer_state: Option<TimerState>,
+}
+
+impl<W> LoadRemover<W> {
+    /// Creates a load remover without predicates.
+    pub const fn new() -> Self {
+        Self {
+            predicates: Vec::new(),
+            loading: DebouncedBool::new(Debounce::OFF),
+            paused: None,
+            timer_state: None,
+        }
+    }
+
+    /// Adds a predicate that tells whether the game is loading.
+    pub fn push(&mut self, predicate: impl Fn(&W) -> Option<bool> + 'static) {
+        self.predicates.push(Box::new(predicate));
+    }
+
+    /// Changes how long the loading state has to hold before game time is
+    /// paused or resumed.
+    pub fn set_debounce(&mut self, debounce: Debounce) {
+        self.loading.set_debounce(debounce);
+    }
+
+    /// Pauses or resumes game time according to the predicates.
+    pub fn update(&mut self, watchers: &W, timer: &mut impl Timer) {
+        let mut loading = Some(false);
+        for predicate in &self.predicates {
+            match predicate(watchers) {
+                Some(true) => {
+                    loading = Some(true);
+                    break;
+                }
+                Some(false) => {}
+                None => loading = None,
+            }
+        }
+
+        if let Some(loading) = self.loading.update(loading) {
+            let reason = if loading { "loading" } else { "done loading" };
+            self.set_paused(loading, reason, timer);
+        }
+    }
+
+    /// Pauses game time while the game isn't running. Call this before
+    /// waiting for the game to start.
+    pub fn detach(&mut self, timer: &mut impl Timer) {
+        self.set_paused(true, "game closed", timer);
+    }
+
+    /// Resumes game time once the game is running again. Call this after
+    /// attaching to it.
+    pub fn attach(&mut self, timer: &mut impl Timer) {
+        self.set_paused(false, "game running", timer);
+    }
+
+    fn set_paused(&mut self, paused: bool, reason: &str, timer: &mut impl Timer) {
+        let timer_state = timer.state();
+        if self.paused == Some(paused) && self.timer_state == Some(timer_state) {
+            return;
+        }
+
+        if paused {
+            timer.pause_game_time_because(format_args!("{reason}"));
+        } else {
+            timer.resume_game_time_because(format_args!("{reason}"));
+        }
+        self.paused = Some(paused);
+        self.timer_state = Some(timer_state);
+    }
+}
+
+impl<W> Default for LoadRemover<W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RecordingTimer, TimerAction};
+
+    #[test]
+    fn pauses_and_resumes_only_on_changes() {
+        let mut load_remover = LoadRemover::<(Option<u16>, Option<u16>)>::new();
+        load_remover.push(|&(loading, _)| Some(loading? == 4));
+        load_remover.push(|&(_, video)| Some(video? == 256));
+        let mut timer = RecordingTimer::new();
+
+        load_remover.detach(&mut timer);
+        load_remover.attach(&mut timer);
+        for watchers in [
+            (None, Some(0)),
+            (Some(1), Some(0)),
+            (Some(4), Some(0)),
+            (Some(4), None),
+            (Some(1), Some(256)),
+            (Some(1), Some(0)),
+            (Some(1), Some(0)),
+        ] {
+            load_remover.update(&watchers, &mut timer);
+        }
+        assert_eq!(
+            timer.take_actions(),
+            [
+                TimerAction::PauseGameTime,
+                TimerAction::ResumeGameTime,
+                TimerAction::PauseGameTime,
+                TimerAction::ResumeGameTime,
+            ]
+        );
+
+        // Starting the run makes LiveSplit forget that game time was paused.
+        load_remover.update(&(Some(4), Some(0)), &mut timer);
+        timer.start();
+        load_remover.update(&(Some(4), Some(0)), &mut timer);
+        assert_eq!(
+            timer.take_actions(),
+            [
+                TimerAction::PauseGameTime,
+                TimerAction::Start,
+                TimerAction::PauseGameTime,
+            ]
+        );
+    }
+
+    #[test]
+    fn debounces_the_loading_state() {
+        let mut load_remover = LoadRemover::<bool>::new();
+        load_remover.push(|&loading| Some(loading));
+        load_remover.set_debounce(Debounce::Ticks(2));
+        let mut timer = RecordingTimer::new();
+
+        for loading in [false, true, false, true, true, true, false, false] {
+            load_remover.update(&loading, &mut timer);
+        }
+        assert_eq!(
+            timer.take_actions(),
+            [
+                TimerAction::ResumeGameTime,
+                TimerAction::PauseGameTime,
+                TimerAction::ResumeGameTime,
+            ]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Leveled logging to the auto splitting runtime's log.
+//!
+//! Messages at or above the importance set with [`set_level`] are printed
+//! through [`asr::print_message`], prefixed with their level. The
+//! [`info!`](crate::info) macro and its siblings format a message for their
+//! level.
+
+use alloc::{format, string::String};
+use core::{
+    fmt,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// How important a message is, from most to least.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    /// Every level, from most to least important.
+    pub const ALL: &'static [Self] = &[
+        Self::Error,
+        Self::Warn,
+        Self::Info,
+        Self::Debug,
+        Self::Trace,
+    ];
+
+    /// Returns the lowercase name of the level.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+        }
+    }
+
+    /// Returns the level with the given lowercase name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|level| level.name() == name)
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+/// Sets the least important level that is still printed.
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Returns the least important level that is still printed.
+pub fn level() -> Level {
+    Level::ALL[usize::from(LEVEL.load(Ordering::Relaxed))]
+}
+
+/// Returns whether messages at `level` are printed.
+pub fn enabled(level: Level) -> bool {
+    level <= self::level()
+}
+
+/// Prints a message at `level`, if that level is enabled. The macros such as
+/// [`info!`](crate::info) call this.
+pub fn log(level: Level, args: fmt::Arguments<'_>) {
+    if enabled(level) {
+        print(&message(level, args));
+    }
+}
+
+fn message(level: Level, args: fmt::Arguments<'_>) -> String {
+    format!("[{level}] {args}")
+}
+
+#[cfg(target_family = "wasm")]
+fn print(message: &str) {
+    asr::print_message(message);
+}
+
+// Native tests, including those of the splitters, can't reach the runtime's
+// log.
+#[cfg(not(target_family = "wasm"))]
+fn print(_: &str) {}
+
+/// Logs a message at the given [`Level`](crate::log::Level).
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)+) => {
+        $crate::log::log($level, format_args!($($arg)+))
+    };
+}
+
+/// Logs a message at [`Level::Error`](crate::log::Level::Error).
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Error, $($arg)+)
+    };
+}
+
+/// Logs a message at [`Level::Warn`](crate::log::Level::Warn).
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Warn, $($arg)+)
+    };
+}
+
+/// Logs a message at [`Level::Info`](crate::log::Level::Info).
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Info, $($arg)+)
+    };
+}
+
+/// Logs a message at [`Level::Debug`](crate::log::Level::Debug).
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Debug, $($arg)+)
+    };
+}
+
+/// Logs a message at [`Level::Trace`](crate::log::Level::Trace).
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Trace, $($arg)+)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_and_formats_by_level() {
+        set_level(Level::Warn);
+        assert!(enabled(Level::Error) && enabled(Level::Warn));
+        assert!(!enabled(Level::Info));
+        set_level(Level::Info);
+        assert_eq!(level(), Level::Info);
+
+        assert_eq!(Level::from_name("debug"), Some(Level::Debug));
+        assert_eq!(Level::from_name("verbose"), None);
+        assert_eq!(
+            message(Level::Info, format_args!("split: {}", "luigis_girls")),
+            "[info] split: luigis_girls"
+        );
+    }
+}
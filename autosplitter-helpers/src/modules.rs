@@ -0,0 +1,39 @@
+use alloc::collections::BTreeMap;
+use asr::Address;
+
+use crate::MemorySource;
+
+/// Base addresses of the modules named by memory paths, looked up once per
+/// process.
+#[derive(Default)]
+pub(crate) struct ModuleBases {
+    bases: BTreeMap<&'static str, Address>,
+}
+
+impl ModuleBases {
+    pub(crate) const fn new() -> Self {
+        Self {
+            bases: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the base address of `module`, looking it up if it isn't cached
+    /// yet. Failed lookups aren't cached, so a module that loads late is found
+    /// once it's there.
+    pub(crate) fn get<S: MemorySource + ?Sized>(
+        &mut self,
+        memory: &S,
+        module: &'static str,
+    ) -> Option<Address> {
+        if let Some(&base) = self.bases.get(module) {
+            return Some(base);
+        }
+        let base = memory.module_address(module)?;
+        self.bases.insert(module, base);
+        Some(base)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.bases.clear();
+    }
+}
@@ -0,0 +1,334 @@
+//! Reading identifiers from the PE headers of a module in memory.
+//!
+//! Unlike the readers in [`asr::file_format::pe`], these work with any
+//! [`MemorySource`], so version detection can be tested against
+//! [`FakeMemory`](crate::FakeMemory).
+
+use alloc::{vec, vec::Vec};
+use asr::{Address, PointerSize};
+use core::str;
+
+use crate::MemorySource;
+
+/// The resource type of version information.
+const RT_VERSION: u32 = 16;
+/// The index of the resource table in the data directories.
+const RESOURCE_DIRECTORY: u64 = 2;
+/// The signature that starts a `VS_FIXEDFILEINFO`.
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xFEEF04BD;
+
+/// Returns the address of the module's `IMAGE_NT_HEADERS`.
+fn nt_headers<S: MemorySource + ?Sized>(memory: &S, module: Address) -> Option<Address> {
+    if memory.read::<[u8; 2]>(module)? != *b"MZ" {
+        return None;
+    }
+    let headers = module + u64::from(memory.read::<u32>(module + 0x3Cu64)?);
+    (memory.read::<[u8; 4]>(headers)? == *b"PE\0\0").then_some(headers)
+}
+
+/// Returns the address of the module's optional header.
+fn optional_header<S: MemorySource + ?Sized>(memory: &S, module: Address) -> Option<Address> {
+    Some(nt_headers(memory, module)? + 0x18u64)
+}
+
+/// Returns the RVA and size of one of the module's data directories.
+fn data_directory<S: MemorySource + ?Sized>(
+    memory: &S,
+    module: Address,
+    index: u64,
+) -> Option<(u32, u32)> {
+    let optional_header = optional_header(memory, module)?;
+    let directories = match memory.read::<u16>(optional_header)? {
+        0x10B => optional_header + 0x60u64,
+        0x20B => optional_header + 0x70u64,
+        _ => return None,
+    };
+    let directory = directories + index * 8;
+    Some((memory.read(directory)?, memory.read(directory + 0x4u64)?))
+}
+
+/// Reads the `SizeOfImage` field of the module's optional header.
+pub fn read_size_of_image<S: MemorySource + ?Sized>(memory: &S, module: Address) -> Option<u32> {
+    memory.read(optional_header(memory, module)? + 0x38u64)
+}
+
+/// Reads the `TimeDateStamp` the linker wrote into the module's file header.
+pub fn read_timestamp<S: MemorySource + ?Sized>(memory: &S, module: Address) -> Option<u32> {
+    memory.read(nt_headers(memory, module)? + 0x8u64)
+}
+
+/// Detects the pointer size of the module's code from the machine type in its
+/// file header. Returns `None` if the machine type is unknown.
+pub fn read_pointer_size<S: MemorySource + ?Sized>(
+    memory: &S,
+    module: Address,
+) -> Option<PointerSize> {
+    match memory.read::<u16>(nt_headers(memory, module)? + 0x4u64)? {
+        // AMD64 and ARM64
+        0x8664 | 0xAA64 => Some(PointerSize::Bit64),
+        // i386
+        0x14C => Some(PointerSize::Bit32),
+        _ => None,
+    }
+}
+
+/// A section of a module, as described by its section header.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    /// The section's name, padded with zeros.
+    pub name: [u8; 8],
+    /// The section's address relative to the module's base address.
+    pub virtual_address: u32,
+    /// The section's size in memory.
+    pub virtual_size: u32,
+}
+
+impl Section {
+    /// Returns the section's name without its padding.
+    pub fn name(&self) -> &str {
+        let len = self.name.iter().position(|&b| b == 0).unwrap_or(8);
+        str::from_utf8(&self.name[..len]).unwrap_or_default()
+    }
+}
+
+/// Reads the module's section headers.
+pub fn read_sections<S: MemorySource + ?Sized>(
+    memory: &S,
+    module: Address,
+) -> Option<Vec<Section>> {
+    let headers = nt_headers(memory, module)?;
+    let count = memory.read::<u16>(headers + 0x6u64)?;
+    let optional_header_size = memory.read::<u16>(headers + 0x14u64)?;
+    let table = headers + 0x18u64 + u64::from(optional_header_size);
+    (0..u64::from(count))
+        .map(|i| {
+            let header = table + i * 0x28;
+            Some(Section {
+                name: memory.read(header)?,
+                virtual_size: memory.read(header + 0x8u64)?,
+                virtual_address: memory.read(header + 0xCu64)?,
+            })
+        })
+        .collect()
+}
+
+/// Computes the CRC-32 of a section's bytes as loaded in memory.
+///
+/// Code sections only change between builds, so this tells apart builds that
+/// share an image size, as long as nothing patched the code in memory.
+pub fn read_section_checksum<S: MemorySource + ?Sized>(
+    memory: &S,
+    module: Address,
+    name: &str,
+) -> Option<u32> {
+    const CHUNK_LEN: u32 = 0x10000;
+
+    let section = read_sections(memory, module)?
+        .into_iter()
+        .find(|section| section.name() == name)?;
+    let start = module + u64::from(section.virtual_address);
+    let mut buf = vec![0; CHUNK_LEN as usize];
+    let mut crc = !0;
+    for offset in (0..section.virtual_size).step_by(CHUNK_LEN as usize) {
+        let chunk = &mut buf[..(section.virtual_size - offset).min(CHUNK_LEN) as usize];
+        memory.read_bytes(start + u64::from(offset), chunk)?;
+        crc = crc32_update(crc, chunk);
+    }
+    Some(!crc)
+}
+
+/// Feeds bytes into a CRC-32 (IEEE) that starts at `!0` and ends inverted.
+fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB88320 & (crc & 1).wrapping_neg());
+        }
+    }
+    crc
+}
+
+/// Returns the offset of the first entry of a resource directory with the
+/// given ID, or of its first entry if `id` is `None`.
+fn resource_entry<S: MemorySource + ?Sized>(
+    memory: &S,
+    directory: Address,
+    id: Option<u32>,
+) -> Option<u32> {
+    let named = memory.read::<u16>(directory + 0xCu64)?;
+    let ids = memory.read::<u16>(directory + 0xEu64)?;
+    (0..u64::from(named) + u64::from(ids))
+        .map(|i| directory + 0x10u64 + i * 8)
+        .find_map(|entry| {
+            let name = memory.read::<u32>(entry)?;
+            if id.is_some_and(|id| id != name) {
+                return None;
+            }
+            memory.read(entry + 0x4u64)
+        })
+}
+
+/// Returns the address of the module's `VS_FIXEDFILEINFO`.
+fn fixed_file_info<S: MemorySource + ?Sized>(memory: &S, module: Address) -> Option<Address> {
+    const SUBDIRECTORY: u32 = 0x8000_0000;
+
+    let (rva, _) = data_directory(memory, module, RESOURCE_DIRECTORY)?;
+    let root = module + u64::from(rva);
+
+    // The version resource sits at type/name/language, so follow the
+    // RT_VERSION type and then the first name and language.
+    let mut directory = root;
+    for id in [Some(RT_VERSION), None] {
+        let offset = resource_entry(memory, directory, id)?;
+        if offset & SUBDIRECTORY == 0 {
+            return None;
+        }
+        directory = root + u64::from(offset & !SUBDIRECTORY);
+    }
+    let offset = resource_entry(memory, directory, None)?;
+    if offset & SUBDIRECTORY != 0 {
+        return None;
+    }
+    let version_info = module + u64::from(memory.read::<u32>(root + u64::from(offset))?);
+
+    // VS_VERSIONINFO starts with three words and the "VS_VERSION_INFO" key,
+    // padded to a 32-bit boundary.
+    let info = version_info + 0x28u64;
+    (memory.read::<u32>(info)? == FIXED_FILE_INFO_SIGNATURE).then_some(info)
+}
+
+/// Splits the two halves of a `VS_FIXEDFILEINFO` version into its parts.
+fn version_parts(most_significant: u32, least_significant: u32) -> [u16; 4] {
+    [
+        (most_significant >> 16) as u16,
+        most_significant as u16,
+        (least_significant >> 16) as u16,
+        least_significant as u16,
+    ]
+}
+
+/// Reads the file version from the module's version resource, e.g.
+/// `[1, 0, 4, 0]` for version 1.0.4.0.
+pub fn read_file_version<S: MemorySource + ?Sized>(
+    memory: &S,
+    module: Address,
+) -> Option<[u16; 4]> {
+    let info = fixed_file_info(memory, module)?;
+    Some(version_parts(
+        memory.read(info + 0x8u64)?,
+        memory.read(info + 0xCu64)?,
+    ))
+}
+
+/// Reads the product version from the module's version resource. This is
+/// often the version shown to players, while the file version is the
+/// build's.
+pub fn read_product_version<S: MemorySource + ?Sized>(
+    memory: &S,
+    module: Address,
+) -> Option<[u16; 4]> {
+    let info = fixed_file_info(memory, module)?;
+    Some(version_parts(
+        memory.read(info + 0x10u64)?,
+        memory.read(info + 0x14u64)?,
+    ))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::FakeMemory;
+
+    /// Writes the headers of a 32-bit module with one `.rsrc` section holding
+    /// a version resource.
+    pub(crate) fn write_module(
+        memory: &mut FakeMemory,
+        base: u64,
+        size_of_image: u32,
+        timestamp: u32,
+        file_version: [u16; 4],
+    ) {
+        let version = |parts: [u16; 4]| {
+            (
+                u32::from(parts[0]) << 16 | u32::from(parts[1]),
+                u32::from(parts[2]) << 16 | u32::from(parts[3]),
+            )
+        };
+
+        memory.write_bytes(base, &[0; 0x1100]);
+        memory.write_bytes(base, b"MZ");
+        memory.write(base + 0x3C, 0x80u32);
+        memory.write_bytes(base + 0x80, b"PE\0\0");
+        memory.write(base + 0x84, 0x14Cu16);
+        memory.write(base + 0x86, 1u16);
+        memory.write(base + 0x88, timestamp);
+        memory.write(base + 0x94, 0xE0u16);
+        memory.write(base + 0x98, 0x10Bu16);
+        memory.write(base + 0xD0, size_of_image);
+        memory.write(base + 0x108, [0x1000u32, 0x100]);
+
+        memory.write_bytes(base + 0x178, b".rsrc\0\0\0");
+        memory.write(base + 0x180, [0x100u32, 0x1000, 0x100, 0x400]);
+
+        // type 16 -> name 1 -> language 0x409 -> data
+        memory.write(base + 0x100E, 1u16);
+        memory.write(base + 0x1010, [RT_VERSION, 0x8000_0018]);
+        memory.write(base + 0x1026, 1u16);
+        memory.write(base + 0x1028, [1u32, 0x8000_0030]);
+        memory.write(base + 0x103E, 1u16);
+        memory.write(base + 0x1040, [0x409u32, 0x48]);
+        memory.write(base + 0x1048, [0x1060u32, 0x5C]);
+        memory.write(base + 0x1088, FIXED_FILE_INFO_SIGNATURE);
+        let (ms, ls) = version(file_version);
+        memory.write(base + 0x1090, [ms, ls, ms, ls]);
+    }
+
+    #[test]
+    fn reads_header_fields_and_the_file_version() {
+        let mut memory = FakeMemory::new();
+        write_module(&mut memory, 0x400000, 0x5E9000, 0x3C8B2F1A, [1, 0, 4, 0]);
+        let module = Address::new(0x400000);
+
+        assert_eq!(read_size_of_image(&memory, module), Some(0x5E9000));
+        assert_eq!(read_timestamp(&memory, module), Some(0x3C8B2F1A));
+        assert_eq!(read_file_version(&memory, module), Some([1, 0, 4, 0]));
+        assert_eq!(read_product_version(&memory, module), Some([1, 0, 4, 0]));
+
+        memory.write_bytes(0x400000u64, b"ZM");
+        assert_eq!(read_size_of_image(&memory, module), None);
+    }
+
+    #[test]
+    fn detects_the_pointer_size_from_the_machine_type() {
+        let mut memory = FakeMemory::new();
+        write_module(&mut memory, 0x400000, 0x2000, 0, [1, 0, 0, 0]);
+        let module = Address::new(0x400000);
+        assert_eq!(read_pointer_size(&memory, module), Some(PointerSize::Bit32));
+
+        memory.write(0x400084u64, 0x8664u16);
+        assert_eq!(read_pointer_size(&memory, module), Some(PointerSize::Bit64));
+
+        memory.write(0x400084u64, 0x1C0u16);
+        assert_eq!(read_pointer_size(&memory, module), None);
+    }
+
+    #[test]
+    fn checksums_sections_by_name() {
+        let mut memory = FakeMemory::new();
+        write_module(&mut memory, 0x400000, 0x2000, 0, [1, 0, 0, 0]);
+        let module = Address::new(0x400000);
+
+        let sections = read_sections(&memory, module).unwrap();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].name(), ".rsrc");
+
+        let mut bytes = [0; 0x100];
+        memory.read_bytes(Address::new(0x401000), &mut bytes);
+        assert_eq!(
+            read_section_checksum(&memory, module, ".rsrc"),
+            Some(!crc32_update(!0, &bytes))
+        );
+        assert_eq!(read_section_checksum(&memory, module, ".text"), None);
+        assert_eq!(!crc32_update(!0, b"123456789"), 0xCBF43926);
+    }
+}
@@ -0,0 +1,179 @@
+use alloc::{boxed::Box, vec::Vec};
+use asr::{Address, PointerSize};
+use bytemuck::CheckedBitPattern;
+use core::{any::Any, marker::PhantomData, ops::Index};
+
+use crate::{
+    batch::{self, RawWatcher},
+    modules::ModuleBases,
+    MemoryPath, MemorySource, MemoryWatcher, StalePolicy,
+};
+
+/// A watcher of any value type, as stored in a [`WatcherRegistry`].
+trait AnyWatcher: RawWatcher {
+    fn set_pointer_size(&mut self, pointer_size: PointerSize);
+    fn set_stale_policy(&mut self, policy: StalePolicy);
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: CheckedBitPattern + 'static> AnyWatcher for MemoryWatcher<T> {
+    fn set_pointer_size(&mut self, pointer_size: PointerSize) {
+        MemoryWatcher::set_pointer_size(self, pointer_size);
+    }
+
+    fn set_stale_policy(&mut self, policy: StalePolicy) {
+        MemoryWatcher::set_stale_policy(self, policy);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// A handle to a watcher of values of type `T` in a [`WatcherRegistry`],
+/// returned when it is inserted.
+pub struct RegistryKey<T> {
+    index: usize,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for RegistryKey<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RegistryKey<T> {}
+
+/// A collection of memory watchers of mixed value types.
+///
+/// Inserting a watcher returns a [`RegistryKey`] that looks it up with its
+/// value type. Watchers at nearby offsets are read together, and base
+/// addresses of the modules named by the watchers' paths are cached until
+/// [`forget_modules`](Self::forget_modules) is called.
+pub struct WatcherRegistry {
+    watchers: Vec<(&'static str, Box<dyn AnyWatcher>)>,
+    modules: ModuleBases,
+}
+
+impl WatcherRegistry {
+    /// Creates an empty registry.
+    pub const fn new() -> Self {
+        Self {
+            watchers: Vec::new(),
+            modules: ModuleBases::new(),
+        }
+    }
+
+    /// Adds a watcher of values of type `T`, named `name` in panic messages.
+    pub fn insert<T: CheckedBitPattern + 'static>(
+        &mut self,
+        name: &'static str,
+        path: impl Into<MemoryPath>,
+    ) -> RegistryKey<T> {
+        self.watchers
+            .push((name, Box::new(MemoryWatcher::<T>::new(path))));
+        RegistryKey {
+            index: self.watchers.len() - 1,
+            value: PhantomData,
+        }
+    }
+
+    /// Returns the watcher of `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` was returned by another registry.
+    pub fn get<T: 'static>(&self, key: RegistryKey<T>) -> &MemoryWatcher<T> {
+        self.watchers
+            .get(key.index)
+            .and_then(|(_, watcher)| watcher.as_any().downcast_ref())
+            .unwrap_or_else(|| panic!("registry key {} is from another registry", key.index))
+    }
+
+    /// Returns the name the watcher of `key` was inserted under.
+    pub fn name<T>(&self, key: RegistryKey<T>) -> Option<&'static str> {
+        self.watchers.get(key.index).map(|&(name, _)| name)
+    }
+
+    /// Changes the pointer size of every watcher currently in the registry.
+    pub fn set_pointer_size(&mut self, pointer_size: PointerSize) {
+        for (_, watcher) in &mut self.watchers {
+            watcher.set_pointer_size(pointer_size);
+        }
+    }
+
+    /// Changes the stale policy of every watcher currently in the registry.
+    pub fn set_stale_policy(&mut self, policy: StalePolicy) {
+        for (_, watcher) in &mut self.watchers {
+            watcher.set_stale_policy(policy);
+        }
+    }
+
+    /// Forgets the cached module base addresses. Call this when attaching to
+    /// a new process if the registry outlives the old one.
+    pub fn forget_modules(&mut self) {
+        self.modules.clear();
+    }
+
+    /// Updates every watcher in the registry. Watchers at nearby offsets are
+    /// read together.
+    pub fn update_all<S: MemorySource + ?Sized>(&mut self, memory: &S, base: Address) {
+        batch::update_batched(
+            self.watchers.iter_mut().map(|(_, watcher)| &mut **watcher),
+            memory,
+            base,
+            &mut self.modules,
+        );
+    }
+}
+
+impl Default for WatcherRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: 'static> Index<RegistryKey<T>> for WatcherRegistry {
+    type Output = MemoryWatcher<T>;
+
+    fn index(&self, key: RegistryKey<T>) -> &Self::Output {
+        self.get(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FakeMemory;
+    use asr::string::ArrayWString;
+
+    #[test]
+    fn updates_and_looks_up_watchers_of_mixed_types() {
+        let mut memory = FakeMemory::new();
+        let base = Address::new(0x1000);
+        let mut registry = WatcherRegistry::new();
+        let game_state = registry.insert::<u32>("game_state", 0x10);
+        let te_helipad = registry.insert::<u8>("te_helipad", 0x14);
+        let mission_text = registry.insert::<ArrayWString<4>>("mission_text", 0x20);
+        let pointer = registry.insert::<u16>("pointer", [0x30, 0x2]);
+        memory.write_bytes(0x1010u64, &[0; 0x20]);
+        memory.write_pointer(0x1030u64, 0x2000u64, PointerSize::Bit32);
+
+        for (state, text) in [(8u32, [0u16; 4]), (9, [0x41, 0x42, 0, 0])] {
+            memory.write(0x1010u64, state);
+            memory.write(0x1014u64, 1u8);
+            memory.write(0x1020u64, text);
+            memory.write(0x2002u64, state as u16);
+            registry.update_all(&memory, base);
+        }
+
+        assert!(registry[game_state].changed_from_to(&8, &9));
+        assert!(!registry.get(te_helipad).changed());
+        assert!(registry[mission_text].changed());
+        assert!(registry[pointer].changed_to(&9));
+        assert_eq!(registry.name(mission_text), Some("mission_text"));
+        // The direct values were read together, the pointer on its own.
+        assert_eq!(memory.reads(), 2 * 3);
+    }
+}
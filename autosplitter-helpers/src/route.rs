@@ -0,0 +1,105 @@
+use alloc::{string::String, vec::Vec};
+use asr::settings::Map;
+
+/// The splits of a run in the order the runner expects them, by key.
+///
+/// The route has one key for every split, so the next expected key is the one
+/// at the timer's current split index. Undoing or skipping splits in the timer
+/// moves through the route with it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Route {
+    keys: Vec<String>,
+}
+
+/// How an event relates to a [`Route`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RouteCheck<'a> {
+    /// The event is the next one in the route.
+    Next,
+    /// The event is in the route, but a different one is expected next.
+    OutOfOrder { expected: Option<&'a str> },
+    /// The event isn't in the route.
+    NotInRoute,
+}
+
+impl Route {
+    /// Creates a route from its keys in order.
+    pub fn new(keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            keys: keys.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Parses a route from keys separated by commas or whitespace.
+    pub fn parse(text: &str) -> Self {
+        Self::new(
+            text.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|key| !key.is_empty()),
+        )
+    }
+
+    /// Loads a route from a setting holding either a list of keys or a text
+    /// for [`parse`](Self::parse). Returns `None` if the setting isn't set.
+    pub fn load(settings: &Map, key: &str) -> Option<Self> {
+        let value = settings.get(key)?;
+        if let Some(list) = value.get_list() {
+            return Some(Self::new(list.iter().filter_map(|key| key.get_string())));
+        }
+        value.get_string().map(|text| Self::parse(&text))
+    }
+
+    /// Returns the keys in order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.keys.iter().map(String::as_str)
+    }
+
+    /// Returns the key expected at `split_index`.
+    pub fn expected(&self, split_index: Option<u64>) -> Option<&str> {
+        let index = usize::try_from(split_index?).ok()?;
+        self.keys.get(index).map(String::as_str)
+    }
+
+    /// Checks whether the event `key` is the next one when the timer is at
+    /// `split_index`.
+    pub fn check(&self, key: &str, split_index: Option<u64>) -> RouteCheck<'_> {
+        let expected = self.expected(split_index);
+        if expected == Some(key) {
+            RouteCheck::Next
+        } else if self.keys.iter().any(|route_key| route_key == key) {
+            RouteCheck::OutOfOrder { expected }
+        } else {
+            RouteCheck::NotInRoute
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expects_the_key_at_the_split_index() {
+        let route = Route::parse("luigis_girls, hidden_packages_each\nbtg_final_split");
+        assert_eq!(
+            route.keys().collect::<Vec<_>>(),
+            ["luigis_girls", "hidden_packages_each", "btg_final_split"]
+        );
+
+        assert_eq!(route.check("luigis_girls", Some(0)), RouteCheck::Next);
+        assert_eq!(
+            route.check("btg_final_split", Some(1)),
+            RouteCheck::OutOfOrder {
+                expected: Some("hidden_packages_each")
+            }
+        );
+        assert_eq!(route.check("the_crook", Some(1)), RouteCheck::NotInRoute);
+        assert_eq!(
+            route.check("btg_final_split", Some(3)),
+            RouteCheck::OutOfOrder { expected: None }
+        );
+        assert_eq!(
+            route.check("luigis_girls", None),
+            RouteCheck::OutOfOrder { expected: None }
+        );
+    }
+}
@@ -0,0 +1,263 @@
+//! Splits declared as data instead of hand-written loops.
+//!
+//! A [`Rule`] names a watcher, a [`Predicate`] over its old/current pair and
+//! the setting that enables it. A [`RuleSet`] checks every rule once per tick
+//! against a [`MemoryWatcherMap`](crate::MemoryWatcherMap) or
+//! [`KeyedWatchers`](crate::KeyedWatchers) and splits for the ones that match.
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use asr::{settings::Map, watcher::Pair};
+use bytemuck::CheckedBitPattern;
+use core::fmt::Display;
+
+use crate::{SplitGuard, Timer, WatcherLookup};
+
+/// Boolean settings that enable or disable rules.
+pub trait SettingSource {
+    /// Returns the value of a boolean setting, if it is set.
+    fn get_bool(&self, key: &str) -> Option<bool>;
+}
+
+impl SettingSource for Map {
+    fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key)?.get_bool()
+    }
+}
+
+impl SettingSource for BTreeMap<&str, bool> {
+    fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key).copied()
+    }
+}
+
+/// An integer type that rules can be evaluated over.
+pub trait RuleValue: Copy + Ord + Display + CheckedBitPattern {
+    /// Adds `rhs`, returning `None` on overflow.
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_rule_value {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl RuleValue for $ty {
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$ty>::checked_add(self, rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_rule_value!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+/// A condition on a watcher's old/current pair.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Predicate<T> {
+    /// The value went up by any amount.
+    Increased,
+    /// The value went up by exactly this amount.
+    IncreasedBy(T),
+    /// The value changed to this one.
+    ChangedTo(T),
+    /// The value changed from the first value to the second.
+    ChangedFromTo(T, T),
+    /// The value went from below this maximum to at least it.
+    Reached(T),
+}
+
+impl<T: RuleValue> Predicate<T> {
+    /// Returns whether the pair satisfies this predicate.
+    pub fn matches(&self, pair: &Pair<T>) -> bool {
+        match *self {
+            Self::Increased => pair.current > pair.old,
+            Self::IncreasedBy(amount) => pair.old.checked_add(amount) == Some(pair.current),
+            Self::ChangedTo(value) => pair.old != value && pair.current == value,
+            Self::ChangedFromTo(old, current) => pair.old == old && pair.current == current,
+            Self::Reached(max) => pair.old < max && pair.current >= max,
+        }
+    }
+}
+
+/// How often a rule may split during a run.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Frequency {
+    /// The rule splits at most once.
+    Once,
+    /// The rule splits at most once for every value the watcher reaches.
+    EachValue,
+}
+
+/// A split declared as data.
+///
+/// `K` is the key of the watcher the rule is evaluated against: a name for a
+/// [`MemoryWatcherMap`](crate::MemoryWatcherMap) or a [`WatcherKey`](crate::WatcherKey) for keyed
+/// watchers.
+pub struct Rule<T, K = &'static str> {
+    /// The watcher the rule is evaluated against.
+    pub watcher: K,
+    /// The condition that triggers the split.
+    pub predicate: Predicate<T>,
+    /// The boolean setting that enables the rule.
+    pub setting: String,
+    /// Whether the rule is enabled if the setting isn't set.
+    pub default: bool,
+    /// How often the rule may split.
+    pub frequency: Frequency,
+}
+
+/// A list of rules evaluated against one collection of watchers.
+pub struct RuleSet<T, K = &'static str> {
+    name: &'static str,
+    rules: Vec<Rule<T, K>>,
+    guard: SplitGuard<(usize, Option<T>)>,
+}
+
+impl<T, K> RuleSet<T, K> {
+    /// Creates an empty rule set.
+    pub const fn new() -> Self {
+        Self {
+            name: "rule",
+            rules: Vec::new(),
+            guard: SplitGuard::new(),
+        }
+    }
+
+    /// Returns this rule set with a name that describes its splits in the
+    /// log, such as `mission complete`.
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Adds a rule.
+    pub fn push(&mut self, rule: Rule<T, K>) {
+        self.rules.push(rule);
+    }
+
+    /// Forgets which rules already split. Call this when the timer resets.
+    pub fn clear(&mut self) {
+        self.guard.clear();
+    }
+}
+
+impl<T: RuleValue, K: Copy> RuleSet<T, K> {
+    /// Lets rules whose splits the runner undid split again. See
+    /// [`SplitGuard::sync`].
+    pub fn sync(&mut self, current_split_index: Option<u64>) {
+        self.guard.sync(current_split_index);
+    }
+
+    /// Splits once for every enabled rule whose predicate matches and that
+    /// hasn't split yet. Returns the number of splits.
+    pub fn evaluate(
+        &mut self,
+        watchers: &impl WatcherLookup<K, T>,
+        settings: &impl SettingSource,
+        timer: &mut impl Timer,
+    ) -> usize {
+        self.evaluate_with(watchers, timer, |rule| {
+            settings.get_bool(&rule.setting).unwrap_or(rule.default)
+        })
+    }
+
+    /// Like [`evaluate`](Self::evaluate), but `accept` decides whether a rule
+    /// whose predicate matches splits, instead of its setting. A rule that
+    /// isn't accepted may split later on.
+    pub fn evaluate_with(
+        &mut self,
+        watchers: &impl WatcherLookup<K, T>,
+        timer: &mut impl Timer,
+        mut accept: impl FnMut(&Rule<T, K>) -> bool,
+    ) -> usize {
+        let mut splits = 0;
+        for (index, rule) in self.rules.iter().enumerate() {
+            let Some(pair) = watchers
+                .watcher(rule.watcher)
+                .and_then(|watcher| watcher.pair())
+            else {
+                continue;
+            };
+            if !rule.predicate.matches(pair) {
+                continue;
+            }
+
+            let value = match rule.frequency {
+                Frequency::Once => None,
+                Frequency::EachValue => Some(pair.current),
+            };
+            if self.guard.is_done(&(index, value)) || !accept(rule) {
+                continue;
+            }
+            self.guard.try_split(
+                (index, value),
+                timer,
+                format_args!(
+                    "{} {}, {}→{}",
+                    self.name, rule.setting, pair.old, pair.current
+                ),
+            );
+            splits += 1;
+        }
+        splits
+    }
+}
+
+impl<T, K> Default for RuleSet<T, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FakeMemory, MemoryWatcherMap, RecordingTimer, TimerAction};
+    use asr::Address;
+
+    #[test]
+    fn splits_for_enabled_matching_rules_according_to_frequency() {
+        let mut memory = FakeMemory::new();
+        let base = Address::new(0x1000);
+        let mut watchers = MemoryWatcherMap::<u32>::new();
+        watchers.insert("missions", 0x10);
+        watchers.insert("packages", 0x14);
+
+        let mut rules = RuleSet::new();
+        rules.push(Rule {
+            watcher: "missions",
+            predicate: Predicate::IncreasedBy(1),
+            setting: "missions".into(),
+            default: true,
+            frequency: Frequency::EachValue,
+        });
+        rules.push(Rule {
+            watcher: "packages",
+            predicate: Predicate::Reached(3),
+            setting: "packages_all".into(),
+            default: false,
+            frequency: Frequency::Once,
+        });
+
+        let mut settings = BTreeMap::new();
+        let mut timer = RecordingTimer::new();
+        let mut splits = Vec::new();
+        for (missions, packages) in [(0u32, 0u32), (1, 1), (1, 3), (2, 3), (1, 0), (2, 3)] {
+            if packages == 3 {
+                settings.insert("packages_all", true);
+            }
+            memory.write(0x1010u64, missions);
+            memory.write(0x1014u64, packages);
+            watchers.update_all(&memory, base);
+            splits.push(rules.evaluate(&watchers, &settings, &mut timer));
+        }
+
+        // Mission 2 and the package rule only split the first time around.
+        assert_eq!(splits, [0, 1, 1, 1, 0, 0]);
+        assert_eq!(timer.actions(), alloc::vec![TimerAction::Split; 3]);
+
+        rules.clear();
+        memory.write(0x1010u64, 3u32);
+        watchers.update_all(&memory, base);
+        assert_eq!(rules.evaluate(&watchers, &settings, &mut timer), 1);
+    }
+}
@@ -0,0 +1,64 @@
+//! Setting widgets the splitters share, for use in their `#[derive(Gui)]`
+//! settings.
+
+use asr::settings::Gui;
+use core::time::Duration;
+
+use crate::{log::Level, Debounce};
+
+/// The least important messages printed to the log.
+#[derive(Gui, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LogLevel {
+    /// Errors
+    Error,
+    /// Warnings
+    Warn,
+    /// Info
+    #[default]
+    Info,
+    /// Debug
+    Debug,
+    /// Trace
+    Trace,
+}
+
+impl From<LogLevel> for Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => Level::Error,
+            LogLevel::Warn => Level::Warn,
+            LogLevel::Info => Level::Info,
+            LogLevel::Debug => Level::Debug,
+            LogLevel::Trace => Level::Trace,
+        }
+    }
+}
+
+/// How long the loading flags have to hold before game time is paused or
+/// resumed.
+#[derive(Gui, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoadDebounce {
+    /// Off
+    #[default]
+    Off,
+    /// 2 ticks
+    TwoTicks,
+    /// 4 ticks
+    FourTicks,
+    /// 50 ms
+    FiftyMillis,
+    /// 100 ms
+    HundredMillis,
+}
+
+impl From<LoadDebounce> for Debounce {
+    fn from(debounce: LoadDebounce) -> Self {
+        match debounce {
+            LoadDebounce::Off => Debounce::OFF,
+            LoadDebounce::TwoTicks => Debounce::Ticks(2),
+            LoadDebounce::FourTicks => Debounce::Ticks(4),
+            LoadDebounce::FiftyMillis => Debounce::Duration(Duration::from_millis(50)),
+            LoadDebounce::HundredMillis => Debounce::Duration(Duration::from_millis(100)),
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Finding addresses by scanning a module's code for byte patterns.
+//!
+//! Instead of hard-coding an offset per game version, a [`SignatureTarget`]
+//! describes an instruction that uses the address, and the address is read
+//! from the instruction's operand wherever the instruction ends up:
+//!
+//! ```
+//! use autosplitter_helpers::sigscan::{Operand, Signature, SignatureTarget};
+//!
+//! // mov eax, [rip + disp32]
+//! let target = SignatureTarget {
+//!     module: "game.exe",
+//!     signature: Signature::new("8B 05 ?? ?? ?? ?? 85 C0"),
+//!     operand: Operand::RipRelative {
+//!         offset: 2,
+//!         instruction_len: 6,
+//!     },
+//! };
+//! ```
+
+use alloc::{vec, vec::Vec};
+use asr::Address;
+
+use crate::{MemoryPath, MemorySource};
+
+/// The number of bytes read at once while scanning.
+const CHUNK_LEN: u64 = 0x10000;
+
+/// A byte pattern in which some bytes may be anything.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    bytes: Vec<Option<u8>>,
+}
+
+impl Signature {
+    /// Parses a pattern of space separated hex bytes, with `??` for bytes that
+    /// may be anything, e.g. `"8B 05 ?? ?? ?? ?? 85 C0"`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern is empty or contains anything else.
+    pub fn new(pattern: &str) -> Self {
+        let bytes = pattern
+            .split_ascii_whitespace()
+            .map(|byte| match byte {
+                "??" | "?" => None,
+                _ => Some(
+                    u8::from_str_radix(byte, 16)
+                        .unwrap_or_else(|_| panic!("invalid signature byte \"{byte}\"")),
+                ),
+            })
+            .collect::<Vec<_>>();
+        assert!(!bytes.is_empty(), "empty signature");
+        Self { bytes }
+    }
+
+    /// Returns the position of the first match in `haystack`.
+    pub fn scan(&self, haystack: &[u8]) -> Option<usize> {
+        haystack.windows(self.bytes.len()).position(|window| {
+            window
+                .iter()
+                .zip(&self.bytes)
+                .all(|(&byte, &expected)| expected.is_none_or(|expected| byte == expected))
+        })
+    }
+
+    /// Returns the address of the first match in `len` bytes of memory
+    /// starting at `start`. Chunks of memory that can't be read are skipped.
+    pub fn scan_memory<S: MemorySource + ?Sized>(
+        &self,
+        memory: &S,
+        start: Address,
+        len: u64,
+    ) -> Option<Address> {
+        let overlap = self.bytes.len() as u64 - 1;
+        let mut buf = vec![0; (CHUNK_LEN + overlap) as usize];
+        let mut offset = 0;
+        while offset < len {
+            let chunk_len = (len - offset).min(CHUNK_LEN + overlap);
+            let chunk = &mut buf[..chunk_len as usize];
+            if memory.read_bytes(start + offset, chunk).is_some() {
+                if let Some(position) = self.scan(chunk) {
+                    return Some(start + offset + position as u64);
+                }
+            }
+            offset += CHUNK_LEN;
+        }
+        None
+    }
+}
+
+/// Where the address is stored in the instruction a signature matches.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Operand {
+    /// A 32-bit absolute address `offset` bytes into the match, as used by
+    /// 32-bit code.
+    Absolute { offset: u64 },
+    /// A 32-bit displacement `offset` bytes into the match, relative to the
+    /// end of the instruction, which is `instruction_len` bytes long and
+    /// starts at the match. Used by 64-bit code.
+    RipRelative { offset: u64, instruction_len: u64 },
+}
+
+impl Operand {
+    /// Reads the address the operand of the instruction at `instruction`
+    /// refers to.
+    pub fn read<S: MemorySource + ?Sized>(
+        self,
+        memory: &S,
+        instruction: Address,
+    ) -> Option<Address> {
+        match self {
+            Self::Absolute { offset } => {
+                let address = memory.read::<u32>(instruction + offset)?;
+                Some(Address::new(address.into()))
+            }
+            Self::RipRelative {
+                offset,
+                instruction_len,
+            } => {
+                let displacement = memory.read::<i32>(instruction + offset)?;
+                let next = (instruction + instruction_len).value();
+                Some(Address::new(next.wrapping_add_signed(displacement.into())))
+            }
+        }
+    }
+}
+
+/// An address found through an instruction in a module's code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignatureTarget {
+    /// The module whose image is scanned.
+    pub module: &'static str,
+    /// The pattern matching the instruction.
+    pub signature: Signature,
+    /// Where the instruction stores the address.
+    pub operand: Operand,
+}
+
+impl SignatureTarget {
+    /// Scans the module and returns the address the matched instruction
+    /// refers to.
+    pub fn resolve<S: MemorySource + ?Sized>(&self, memory: &S) -> Option<Address> {
+        let (base, size) = memory.module_range(self.module)?;
+        let instruction = self.signature.scan_memory(memory, base, size)?;
+        self.operand.read(memory, instruction)
+    }
+
+    /// Scans the module and returns a path in it that starts at the address
+    /// the matched instruction refers to and continues with `rest`.
+    pub fn find_path<S: MemorySource + ?Sized>(
+        &self,
+        memory: &S,
+        rest: &[u64],
+    ) -> Option<MemoryPath> {
+        let (base, _) = memory.module_range(self.module)?;
+        let offset = self.resolve(memory)?.value().checked_sub(base.value())?;
+        let offsets = [&[offset], rest].concat();
+        Some(MemoryPath::in_module(
+            self.module,
+            offsets.into_boxed_slice(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FakeMemory, MemoryWatcher};
+
+    #[test]
+    fn finds_paths_through_absolute_and_rip_relative_operands() {
+        let mut memory = FakeMemory::new();
+        memory.map_module("game.exe", 0x400000u64, 0x30000);
+        memory.write_bytes(0x400000u64, &[0xCC; 0x30000]);
+        // mov eax, [0x4200F0] right across the boundary of the first chunk.
+        memory.write_bytes(0x40FFFEu64, &[0xA1, 0xF0, 0x00, 0x42, 0x00]);
+        // mov eax, [rip - 0x10]
+        memory.write_bytes(0x420000u64, &[0x8B, 0x05, 0xF0, 0xFF, 0xFF, 0xFF, 0x85]);
+        memory.write(0x4200F0u64, 7u32);
+        memory.write(0x41FFF6u64, 9u32);
+
+        let absolute = SignatureTarget {
+            module: "game.exe",
+            signature: Signature::new("A1 ?? ?? 42 00"),
+            operand: Operand::Absolute { offset: 1 },
+        };
+        let relative = SignatureTarget {
+            module: "game.exe",
+            signature: Signature::new("8B 05 ?? ?? ?? ?? 85"),
+            operand: Operand::RipRelative {
+                offset: 2,
+                instruction_len: 6,
+            },
+        };
+
+        let path = absolute.find_path(&memory, &[]).unwrap();
+        assert_eq!(path.offsets(), [0x200F0]);
+        assert_eq!(path.module(), Some("game.exe"));
+        let mut watcher = MemoryWatcher::<u32>::new(path);
+        watcher.update(&memory, Address::NULL);
+        assert_eq!(watcher.pair().map(|pair| pair.current), Some(7));
+
+        assert_eq!(relative.resolve(&memory), Some(Address::new(0x41FFF6)));
+        assert_eq!(
+            Signature::new("8B 05 ?? ?? ?? ?? 86").scan_memory(
+                &memory,
+                Address::new(0x400000),
+                0x30000
+            ),
+            None
+        );
+    }
+}
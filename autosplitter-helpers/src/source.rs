@@ -0,0 +1,214 @@
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec,
+};
+use asr::{Address, PointerSize, Process};
+use bytemuck::{CheckedBitPattern, NoUninit};
+use core::{cell::Cell, mem};
+
+/// Something watchers can read memory from.
+///
+/// This is implemented for [`Process`] and for [`FakeMemory`], so splitter
+/// logic can be driven by scripted memory in native tests.
+pub trait MemorySource {
+    /// Fills `buf` with the bytes starting at `address`.
+    fn read_bytes(&self, address: Address, buf: &mut [u8]) -> Option<()>;
+
+    /// Returns the base address of the named module, if it is loaded.
+    fn module_address(&self, name: &str) -> Option<Address> {
+        Some(self.module_range(name)?.0)
+    }
+
+    /// Returns the base address and size of the named module, if it is
+    /// loaded.
+    fn module_range(&self, _name: &str) -> Option<(Address, u64)> {
+        None
+    }
+
+    /// Reads a value at `address`.
+    fn read<T: CheckedBitPattern>(&self, address: Address) -> Option<T> {
+        let mut buf = vec![0; mem::size_of::<T>()];
+        self.read_bytes(address, &mut buf)?;
+        bytemuck::checked::try_pod_read_unaligned(&buf).ok()
+    }
+
+    /// Reads a pointer of the given size at `address`.
+    fn read_pointer(&self, address: Address, pointer_size: PointerSize) -> Option<Address> {
+        Some(Address::new(match pointer_size {
+            PointerSize::Bit16 => self.read::<u16>(address)?.into(),
+            PointerSize::Bit32 => self.read::<u32>(address)?.into(),
+            PointerSize::Bit64 => self.read::<u64>(address)?,
+        }))
+    }
+
+    /// Follows a pointer path from `base` and reads the value at its end.
+    fn read_pointer_path<T: CheckedBitPattern>(
+        &self,
+        base: Address,
+        pointer_size: PointerSize,
+        path: &[u64],
+    ) -> Option<T> {
+        let (&last, path) = path.split_last()?;
+        let mut address = base;
+        for &offset in path {
+            address = self.read_pointer(address + offset, pointer_size)?;
+        }
+        self.read(address + last)
+    }
+}
+
+impl MemorySource for Process {
+    fn read_bytes(&self, address: Address, buf: &mut [u8]) -> Option<()> {
+        self.read_into_buf(address, buf).ok()
+    }
+
+    fn module_address(&self, name: &str) -> Option<Address> {
+        self.get_module_address(name).ok()
+    }
+
+    fn module_range(&self, name: &str) -> Option<(Address, u64)> {
+        self.get_module_range(name).ok()
+    }
+
+    fn read<T: CheckedBitPattern>(&self, address: Address) -> Option<T> {
+        Process::read(self, address).ok()
+    }
+
+    fn read_pointer(&self, address: Address, pointer_size: PointerSize) -> Option<Address> {
+        Process::read_pointer(self, address, pointer_size).ok()
+    }
+
+    fn read_pointer_path<T: CheckedBitPattern>(
+        &self,
+        base: Address,
+        pointer_size: PointerSize,
+        path: &[u64],
+    ) -> Option<T> {
+        Process::read_pointer_path(self, base, pointer_size, path).ok()
+    }
+}
+
+/// A sparse in-memory address space.
+///
+/// Reads of bytes that were never written fail, just like reads of unmapped
+/// memory in a real process. Every read is counted, so tests can check how
+/// many reads a real process would have been asked for.
+#[derive(Default)]
+pub struct FakeMemory {
+    bytes: BTreeMap<u64, u8>,
+    modules: BTreeMap<String, (Address, u64)>,
+    reads: Cell<u64>,
+    module_lookups: Cell<u64>,
+}
+
+impl FakeMemory {
+    /// Creates an empty address space.
+    pub const fn new() -> Self {
+        Self {
+            bytes: BTreeMap::new(),
+            modules: BTreeMap::new(),
+            reads: Cell::new(0),
+            module_lookups: Cell::new(0),
+        }
+    }
+
+    /// Makes a module with the given name and size appear loaded at `base`.
+    pub fn map_module(&mut self, name: &str, base: impl Into<Address>, size: u64) {
+        self.modules.insert(name.to_string(), (base.into(), size));
+    }
+
+    /// Returns the number of reads made so far, including failed ones.
+    pub fn reads(&self) -> u64 {
+        self.reads.get()
+    }
+
+    /// Returns the number of module lookups made so far, including failed
+    /// ones.
+    pub fn module_lookups(&self) -> u64 {
+        self.module_lookups.get()
+    }
+
+    /// Writes raw bytes starting at `address`.
+    pub fn write_bytes(&mut self, address: impl Into<Address>, bytes: &[u8]) {
+        let address = address.into().value();
+        for (offset, &byte) in (0..).zip(bytes) {
+            self.bytes.insert(address + offset, byte);
+        }
+    }
+
+    /// Writes a value at `address`.
+    pub fn write<T: NoUninit>(&mut self, address: impl Into<Address>, value: T) {
+        self.write_bytes(address, bytemuck::bytes_of(&value));
+    }
+
+    /// Writes a pointer of the given size at `address`.
+    pub fn write_pointer(
+        &mut self,
+        address: impl Into<Address>,
+        target: impl Into<Address>,
+        pointer_size: PointerSize,
+    ) {
+        let target = target.into().value();
+        match pointer_size {
+            PointerSize::Bit16 => self.write(address, target as u16),
+            PointerSize::Bit32 => self.write(address, target as u32),
+            PointerSize::Bit64 => self.write(address, target),
+        }
+    }
+
+    /// Unmaps `len` bytes starting at `address`, making reads of them fail.
+    pub fn unmap(&mut self, address: impl Into<Address>, len: u64) {
+        let start = address.into().value();
+        let unmapped = self
+            .bytes
+            .range(start..start.saturating_add(len))
+            .map(|(&address, _)| address)
+            .collect::<alloc::vec::Vec<_>>();
+        for address in unmapped {
+            self.bytes.remove(&address);
+        }
+    }
+}
+
+impl MemorySource for FakeMemory {
+    fn read_bytes(&self, address: Address, buf: &mut [u8]) -> Option<()> {
+        self.reads.set(self.reads.get() + 1);
+        let address = address.value();
+        for (offset, byte) in (0..).zip(buf) {
+            *byte = *self.bytes.get(&address.checked_add(offset)?)?;
+        }
+        Some(())
+    }
+
+    fn module_range(&self, name: &str) -> Option<(Address, u64)> {
+        self.module_lookups.set(self.module_lookups.get() + 1);
+        self.modules.get(name).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_pointer_paths_through_fake_memory() {
+        let mut memory = FakeMemory::new();
+        memory.write_pointer(0x1010u64, 0x2000u64, PointerSize::Bit32);
+        memory.write(0x2004u64, 42u32);
+
+        let base = Address::new(0x1000);
+        assert_eq!(
+            memory.read_pointer_path::<u32>(base, PointerSize::Bit32, &[0x10, 0x4]),
+            Some(42)
+        );
+        assert_eq!(
+            memory.read_pointer_path::<u32>(base, PointerSize::Bit64, &[0x10, 0x4]),
+            None
+        );
+
+        memory.unmap(0x2004u64, 2);
+        assert_eq!(memory.read::<u32>(Address::new(0x2004)), None);
+        assert_eq!(memory.read::<u16>(Address::new(0x2006)), Some(0));
+    }
+}
@@ -0,0 +1,179 @@
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::fmt;
+
+use crate::Timer;
+
+/// Remembers which splits already fired during a run, so each key splits at
+/// most once.
+///
+/// Keys are remembered in the order they split, together with the split index
+/// the timer moved to, which lets the guard forget the most recent ones again
+/// when the runner undoes a split. [`sync`](Self::sync) does that
+/// automatically.
+pub struct SplitGuard<K> {
+    done: BTreeSet<K>,
+    /// Every split key with the split index the timer moved to, or `None` if
+    /// the timer wasn't running.
+    history: Vec<(K, Option<u64>)>,
+}
+
+impl<K> SplitGuard<K> {
+    /// Creates a guard with no completed splits.
+    pub const fn new() -> Self {
+        Self {
+            done: BTreeSet::new(),
+            history: Vec::new(),
+        }
+    }
+
+    /// Returns the number of completed splits.
+    pub fn len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Returns whether no split has been completed yet.
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty()
+    }
+
+    /// Forgets every completed split. Call this when the timer resets.
+    pub fn clear(&mut self) {
+        self.done.clear();
+        self.history.clear();
+    }
+}
+
+impl<K: Ord + Clone> SplitGuard<K> {
+    /// Splits for `key` because of `reason`, returning `false` without
+    /// splitting if `key` already split.
+    pub fn try_split(
+        &mut self,
+        key: K,
+        timer: &mut impl Timer,
+        reason: fmt::Arguments<'_>,
+    ) -> bool {
+        if !self.done.insert(key.clone()) {
+            return false;
+        }
+        timer.split_because(reason);
+        // Other guards may split in the same tick, so only the timer knows
+        // which index this split moved it to.
+        self.history.push((key, timer.current_split_index()));
+        true
+    }
+
+    /// Returns whether `key` has already split.
+    pub fn is_done(&self, key: &K) -> bool {
+        self.done.contains(key)
+    }
+
+    /// Forgets the most recently completed split, so it can fire again.
+    pub fn undo(&mut self) -> Option<K> {
+        let (key, _) = self.history.pop()?;
+        self.done.remove(&key);
+        Some(key)
+    }
+
+    /// Follows the runner undoing splits. Call this every tick, before any
+    /// split, with the timer's current split index.
+    ///
+    /// Splits are tagged with the index the timer moved to when they fired,
+    /// and the ones tagged with an index past `current_split_index` are
+    /// forgotten, so they can fire again.
+    pub fn sync(&mut self, current_split_index: Option<u64>) {
+        let Some(current) = current_split_index else {
+            return;
+        };
+
+        while let Some(&(_, Some(index))) = self.history.last() {
+            if index <= current {
+                break;
+            }
+            self.undo();
+        }
+    }
+}
+
+impl<K> Default for SplitGuard<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RecordingTimer, TimerAction};
+
+    #[test]
+    fn splits_each_key_once_until_undone_or_cleared() {
+        let mut guard = SplitGuard::new();
+        let mut timer = RecordingTimer::new();
+        timer.start();
+
+        assert!(guard.try_split(("mission", 1), &mut timer, format_args!("1")));
+        assert!(guard.try_split(("mission", 2), &mut timer, format_args!("2")));
+        assert!(!guard.try_split(("mission", 1), &mut timer, format_args!("1")));
+        assert_eq!(guard.len(), 2);
+        assert_eq!(
+            timer.take_actions(),
+            [TimerAction::Start, TimerAction::Split, TimerAction::Split]
+        );
+
+        assert_eq!(guard.undo(), Some(("mission", 2)));
+        assert!(guard.is_done(&("mission", 1)));
+        assert!(guard.try_split(("mission", 2), &mut timer, format_args!("2")));
+
+        guard.clear();
+        assert!(guard.is_empty());
+        assert!(guard.try_split(("mission", 1), &mut timer, format_args!("1")));
+    }
+
+    #[test]
+    fn forgets_splits_the_runner_undid() {
+        let mut guard = SplitGuard::new();
+        let mut timer = RecordingTimer::new();
+        timer.start();
+
+        for mission in 1..=3 {
+            guard.sync(timer.current_split_index());
+            assert!(guard.try_split(mission, &mut timer, format_args!("{mission}")));
+        }
+        timer.skip_split();
+        guard.sync(timer.current_split_index());
+        assert_eq!(timer.current_split_index(), Some(4));
+
+        // Undoing the skip leaves every split in place.
+        timer.undo_split();
+        guard.sync(timer.current_split_index());
+        assert_eq!(guard.len(), 3);
+
+        timer.undo_split();
+        timer.undo_split();
+        guard.sync(timer.current_split_index());
+        assert!(guard.is_done(&1));
+        assert!(!guard.is_done(&2) && !guard.is_done(&3));
+        assert!(guard.try_split(2, &mut timer, format_args!("2")));
+    }
+
+    #[test]
+    fn tags_splits_of_several_guards_in_one_tick() {
+        let mut missions = SplitGuard::new();
+        let mut counters = SplitGuard::new();
+        let mut timer = RecordingTimer::new();
+        timer.start();
+
+        missions.sync(timer.current_split_index());
+        counters.sync(timer.current_split_index());
+        assert!(missions.try_split("hundo", &mut timer, format_args!("hundo")));
+        assert!(counters.try_split("fight_club", &mut timer, format_args!("fight club")));
+        assert_eq!(timer.current_split_index(), Some(2));
+
+        // Undoing only the last split of the tick keeps the first one.
+        timer.undo_split();
+        missions.sync(timer.current_split_index());
+        counters.sync(timer.current_split_index());
+        assert!(missions.is_done(&"hundo"));
+        assert!(!counters.is_done(&"fight_club"));
+    }
+}
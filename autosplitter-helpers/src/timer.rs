@@ -0,0 +1,253 @@
+use alloc::{string::String, vec::Vec};
+use asr::timer::{self, TimerState};
+use core::fmt;
+
+/// The timer operations an autosplitter performs.
+///
+/// Splitter logic written against this trait can run on [`AsrTimer`] in the
+/// runtime and on [`RecordingTimer`] in native tests.
+///
+/// The `*_because` methods also log the action together with what triggered
+/// it.
+pub trait Timer {
+    /// Returns the current state of the timer.
+    fn state(&self) -> TimerState;
+
+    /// Returns the index of the current segment, or `None` if the timer isn't
+    /// running.
+    fn current_split_index(&self) -> Option<u64>;
+
+    /// Starts the timer.
+    fn start(&mut self);
+
+    /// Splits the current segment.
+    fn split(&mut self);
+
+    /// Resets the timer.
+    fn reset(&mut self);
+
+    /// Pauses game time.
+    fn pause_game_time(&mut self);
+
+    /// Resumes game time.
+    fn resume_game_time(&mut self);
+
+    /// Sets a custom timer variable.
+    fn set_variable(&mut self, key: &str, value: &str);
+
+    /// Starts the timer and logs why at [`Level::Info`](crate::log::Level).
+    fn start_because(&mut self, reason: fmt::Arguments<'_>) {
+        crate::info!("start: {reason}");
+        self.start();
+    }
+
+    /// Splits and logs why at [`Level::Info`](crate::log::Level).
+    fn split_because(&mut self, reason: fmt::Arguments<'_>) {
+        crate::info!("split: {reason}");
+        self.split();
+    }
+
+    /// Resets the timer and logs why at [`Level::Info`](crate::log::Level).
+    fn reset_because(&mut self, reason: fmt::Arguments<'_>) {
+        crate::info!("reset: {reason}");
+        self.reset();
+    }
+
+    /// Pauses game time and logs why at [`Level::Debug`](crate::log::Level).
+    fn pause_game_time_because(&mut self, reason: fmt::Arguments<'_>) {
+        crate::debug!("pause game time: {reason}");
+        self.pause_game_time();
+    }
+
+    /// Resumes game time and logs why at [`Level::Debug`](crate::log::Level).
+    fn resume_game_time_because(&mut self, reason: fmt::Arguments<'_>) {
+        crate::debug!("resume game time: {reason}");
+        self.resume_game_time();
+    }
+}
+
+/// The LiveSplit timer, driven through the auto splitting runtime.
+#[derive(Default, Copy, Clone)]
+pub struct AsrTimer;
+
+impl Timer for AsrTimer {
+    fn state(&self) -> TimerState {
+        timer::state()
+    }
+
+    fn current_split_index(&self) -> Option<u64> {
+        timer::current_split_index()
+    }
+
+    fn start(&mut self) {
+        timer::start();
+    }
+
+    fn split(&mut self) {
+        timer::split();
+    }
+
+    fn reset(&mut self) {
+        timer::reset();
+    }
+
+    fn pause_game_time(&mut self) {
+        timer::pause_game_time();
+    }
+
+    fn resume_game_time(&mut self) {
+        timer::resume_game_time();
+    }
+
+    fn set_variable(&mut self, key: &str, value: &str) {
+        timer::set_variable(key, value);
+    }
+}
+
+/// An action performed on a [`RecordingTimer`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TimerAction {
+    Start,
+    Split,
+    Reset,
+    PauseGameTime,
+    ResumeGameTime,
+    SetVariable { key: String, value: String },
+}
+
+/// A fake timer that logs every action performed on it.
+///
+/// Starting moves it to [`TimerState::Running`] and resetting moves it back to
+/// [`TimerState::NotRunning`], so logic that checks the state behaves as it
+/// would against LiveSplit. The split index follows starts, splits and resets,
+/// and [`skip_split`](Self::skip_split) and [`undo_split`](Self::undo_split)
+/// simulate the runner moving it.
+pub struct RecordingTimer {
+    state: TimerState,
+    split_index: Option<u64>,
+    actions: Vec<TimerAction>,
+}
+
+impl RecordingTimer {
+    /// Creates a timer that isn't running.
+    pub const fn new() -> Self {
+        Self {
+            state: TimerState::NotRunning,
+            split_index: None,
+            actions: Vec::new(),
+        }
+    }
+
+    /// Overrides the timer state, e.g. to simulate the runner pausing.
+    pub fn set_state(&mut self, state: TimerState) {
+        self.state = state;
+    }
+
+    /// Skips the current segment, like the runner would.
+    pub fn skip_split(&mut self) {
+        if let Some(index) = &mut self.split_index {
+            *index += 1;
+        }
+    }
+
+    /// Goes back to the previous segment, like the runner would.
+    pub fn undo_split(&mut self) {
+        if let Some(index) = &mut self.split_index {
+            *index = index.saturating_sub(1);
+        }
+    }
+
+    /// Returns the actions recorded so far.
+    pub fn actions(&self) -> &[TimerAction] {
+        &self.actions
+    }
+
+    /// Returns and clears the actions recorded so far.
+    pub fn take_actions(&mut self) -> Vec<TimerAction> {
+        core::mem::take(&mut self.actions)
+    }
+}
+
+impl Default for RecordingTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timer for RecordingTimer {
+    fn state(&self) -> TimerState {
+        self.state
+    }
+
+    fn current_split_index(&self) -> Option<u64> {
+        self.split_index
+    }
+
+    fn start(&mut self) {
+        if self.state == TimerState::NotRunning {
+            self.state = TimerState::Running;
+            self.split_index = Some(0);
+        }
+        self.actions.push(TimerAction::Start);
+    }
+
+    fn split(&mut self) {
+        self.skip_split();
+        self.actions.push(TimerAction::Split);
+    }
+
+    fn reset(&mut self) {
+        self.state = TimerState::NotRunning;
+        self.split_index = None;
+        self.actions.push(TimerAction::Reset);
+    }
+
+    fn pause_game_time(&mut self) {
+        self.actions.push(TimerAction::PauseGameTime);
+    }
+
+    fn resume_game_time(&mut self) {
+        self.actions.push(TimerAction::ResumeGameTime);
+    }
+
+    fn set_variable(&mut self, key: &str, value: &str) {
+        self.actions.push(TimerAction::SetVariable {
+            key: key.into(),
+            value: value.into(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_actions_and_tracks_state() {
+        let mut timer = RecordingTimer::new();
+        assert_eq!(timer.state(), TimerState::NotRunning);
+
+        timer.start();
+        timer.split();
+        timer.set_variable("Missions", "1");
+        assert_eq!(timer.state(), TimerState::Running);
+        assert_eq!(timer.current_split_index(), Some(1));
+
+        timer.reset();
+        assert_eq!(timer.state(), TimerState::NotRunning);
+        assert_eq!(timer.current_split_index(), None);
+        assert_eq!(
+            timer.take_actions(),
+            [
+                TimerAction::Start,
+                TimerAction::Split,
+                TimerAction::SetVariable {
+                    key: "Missions".into(),
+                    value: "1".into(),
+                },
+                TimerAction::Reset,
+            ]
+        );
+        assert!(timer.actions().is_empty());
+    }
+}
@@ -0,0 +1,417 @@
+//! Recording and replaying watcher values.
+//!
+//! A trace is a list of JSON lines, one per watcher value change:
+//!
+//! ```text
+//! {"tick":0,"name":"counters/fight_club","value":"03000000"}
+//! {"tick":412,"name":"counters/fight_club","value":"04000000"}
+//! ```
+//!
+//! Values are the raw little-endian bytes of the watched type, and `null`
+//! marks a watcher that has no value or whose read failed. Replaying `null`
+//! feeds a failed read into the watcher, so its stale policy treats the gap
+//! the same way it did live. Recording prints the lines through a
+//! sink such as [`asr::print_message`], and [`TraceReplay`] feeds them back
+//! into the same watchers so a run can be re-simulated in native tests.
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use bytemuck::{CheckedBitPattern, NoUninit};
+use core::fmt::Write;
+
+use crate::{KeyedWatchers, MemoryWatcher, MemoryWatcherMap, WatcherKey};
+
+/// Records watcher value changes, one tick at a time.
+#[derive(Default)]
+pub struct TraceRecorder {
+    tick: u64,
+    last: BTreeMap<String, Option<Vec<u8>>>,
+    lines: Vec<String>,
+}
+
+impl TraceRecorder {
+    /// Creates a recorder starting at tick 0.
+    pub const fn new() -> Self {
+        Self {
+            tick: 0,
+            last: BTreeMap::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    /// Records the watcher's current value if it changed since it was last
+    /// recorded. A failed read is recorded as no value, even if the watcher
+    /// holds on to its last one.
+    pub fn record<T: NoUninit>(&mut self, name: &str, watcher: &MemoryWatcher<T>) {
+        let value = watcher
+            .pair()
+            .filter(|_| !watcher.is_stale())
+            .map(|pair| bytemuck::bytes_of(&pair.current));
+
+        if self.last.get(name).map(Option::as_deref) == Some(value) {
+            return;
+        }
+
+        self.lines.push(format_event(self.tick, name, value));
+        self.last
+            .insert(name.to_string(), value.map(<[u8]>::to_vec));
+    }
+
+    /// Records every watcher in a map under `prefix/name`.
+    pub fn record_map<T: NoUninit>(&mut self, prefix: &str, watchers: &MemoryWatcherMap<T>) {
+        for (name, watcher) in watchers.iter() {
+            self.record(&format!("{prefix}/{name}"), watcher);
+        }
+    }
+
+    /// Records every keyed watcher under `prefix/name`.
+    pub fn record_keyed<K: WatcherKey, T: NoUninit>(
+        &mut self,
+        prefix: &str,
+        watchers: &KeyedWatchers<K, T>,
+    ) {
+        for (key, watcher) in watchers.iter() {
+            self.record(&format!("{prefix}/{}", key.name()), watcher);
+        }
+    }
+
+    /// Passes the lines recorded during this tick to `sink` and moves on to
+    /// the next tick.
+    pub fn finish_tick(&mut self, mut sink: impl FnMut(&str)) {
+        for line in self.lines.drain(..) {
+            sink(&line);
+        }
+        self.tick += 1;
+    }
+}
+
+/// A single value change in a trace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub tick: u64,
+    pub name: String,
+    pub value: Option<Vec<u8>>,
+}
+
+impl TraceEvent {
+    /// Parses a line written by [`TraceRecorder`].
+    pub fn parse(line: &str) -> Option<Self> {
+        let rest = line.trim().strip_prefix("{\"tick\":")?;
+        let (tick, rest) = rest.split_once(",\"name\":\"")?;
+        let (name, rest) = rest.split_once("\",\"value\":")?;
+        let value = rest.strip_suffix('}')?;
+
+        Some(Self {
+            tick: tick.parse().ok()?,
+            name: unescape(name)?,
+            value: match value {
+                "null" => None,
+                hex => Some(decode_hex(hex.strip_prefix('"')?.strip_suffix('"')?)?),
+            },
+        })
+    }
+}
+
+/// An error returned when a trace contains a line that can't be parsed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TraceError {
+    /// The 1-based number of the offending line.
+    pub line: usize,
+}
+
+/// Replays a recorded trace into watchers, one tick at a time.
+pub struct TraceReplay {
+    events: Vec<TraceEvent>,
+    next_event: usize,
+    tick: Option<u64>,
+    values: BTreeMap<String, Option<Vec<u8>>>,
+}
+
+impl TraceReplay {
+    /// Parses a trace. Blank lines and lines that don't start with `{` (such
+    /// as other log output) are skipped.
+    pub fn parse(trace: &str) -> Result<Self, TraceError> {
+        let mut events = Vec::new();
+        for (index, line) in trace.lines().enumerate() {
+            if !line.trim_start().starts_with('{') {
+                continue;
+            }
+            events.push(TraceEvent::parse(line).ok_or(TraceError { line: index + 1 })?);
+        }
+        events.sort_by_key(|event| event.tick);
+
+        Ok(Self {
+            events,
+            next_event: 0,
+            tick: None,
+            values: BTreeMap::new(),
+        })
+    }
+
+    /// Returns the tick the replay is currently at.
+    pub fn tick(&self) -> Option<u64> {
+        self.tick
+    }
+
+    /// Moves to the next tick, applying its value changes. Returns `false`
+    /// once every event has been replayed.
+    pub fn advance(&mut self) -> bool {
+        if self.next_event == self.events.len() {
+            return false;
+        }
+
+        let tick = self.tick.map_or(0, |tick| tick + 1);
+        while let Some(event) = self
+            .events
+            .get(self.next_event)
+            .filter(|event| event.tick <= tick)
+        {
+            self.values.insert(event.name.clone(), event.value.clone());
+            self.next_event += 1;
+        }
+        self.tick = Some(tick);
+        true
+    }
+
+    /// Feeds the recorded value of `name` at the current tick into a watcher.
+    /// Without a value, this is a failed read.
+    pub fn update<T: CheckedBitPattern>(&self, name: &str, watcher: &mut MemoryWatcher<T>) {
+        let value = self
+            .values
+            .get(name)
+            .and_then(Option::as_deref)
+            .and_then(|bytes| bytemuck::checked::try_pod_read_unaligned(bytes).ok());
+        watcher.apply(value);
+    }
+
+    /// Feeds the recorded values of a map's watchers, recorded under
+    /// `prefix/name`.
+    pub fn update_map<T: CheckedBitPattern>(
+        &self,
+        prefix: &str,
+        watchers: &mut MemoryWatcherMap<T>,
+    ) {
+        for (name, watcher) in watchers.watchers.iter_mut() {
+            self.update(&format!("{prefix}/{name}"), watcher);
+        }
+    }
+
+    /// Feeds the recorded values of keyed watchers, recorded under
+    /// `prefix/name`.
+    pub fn update_keyed<K: WatcherKey, T: CheckedBitPattern>(
+        &self,
+        prefix: &str,
+        watchers: &mut KeyedWatchers<K, T>,
+    ) {
+        for &key in K::ALL {
+            self.update(&format!("{prefix}/{}", key.name()), watchers.get_mut(key));
+        }
+    }
+}
+
+fn format_event(tick: u64, name: &str, value: Option<&[u8]>) -> String {
+    let mut line = format!("{{\"tick\":{tick},\"name\":\"");
+    escape_into(&mut line, name);
+    line.push_str("\",\"value\":");
+    match value {
+        Some(bytes) => {
+            line.push('"');
+            for byte in bytes {
+                let _ = write!(line, "{byte:02x}");
+            }
+            line.push_str("\"}");
+        }
+        None => line.push_str("null}"),
+    }
+    line
+}
+
+/// Writes `text` as the contents of a JSON string.
+fn escape_into(line: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '"' => line.push_str("\\\""),
+            '\\' => line.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(line, "\\u{:04x}", u32::from(c));
+            }
+            c => line.push(c),
+        }
+    }
+}
+
+/// Reads the contents of a JSON string written by [`escape_into`].
+fn unescape(text: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        unescaped.push(match chars.next()? {
+            'u' => {
+                let hex = chars.by_ref().take(4).collect::<String>();
+                if hex.len() != 4 {
+                    return None;
+                }
+                char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+            }
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            c @ ('"' | '\\' | '/') => c,
+            _ => return None,
+        });
+    }
+    Some(unescaped)
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FakeMemory, StalePolicy};
+    use asr::Address;
+
+    #[test]
+    fn replays_recorded_changes_into_the_same_watchers() {
+        let mut memory = FakeMemory::new();
+        let base = Address::new(0x1000);
+        let mut counters = MemoryWatcherMap::<u32>::new();
+        counters.insert("fight_club", 0x10);
+        let mut recorder = TraceRecorder::new();
+        let mut trace = String::new();
+
+        for value in [3u32, 3, 4, 4] {
+            memory.write(0x1010u64, value);
+            counters.update_all(&memory, base);
+            recorder.record_map("counters", &counters);
+            recorder.finish_tick(|line| {
+                trace.push_str(line);
+                trace.push('\n');
+            });
+        }
+
+        assert_eq!(
+            trace,
+            "{\"tick\":0,\"name\":\"counters/fight_club\",\"value\":\"03000000\"}\n\
+             {\"tick\":2,\"name\":\"counters/fight_club\",\"value\":\"04000000\"}\n"
+        );
+
+        let mut replay = TraceReplay::parse(&trace).unwrap();
+        let mut replayed = MemoryWatcherMap::<u32>::new();
+        replayed.insert("fight_club", 0x10);
+        let mut increases = Vec::new();
+        while replay.advance() {
+            replay.update_map("counters", &mut replayed);
+            if replayed["fight_club"].increased_by(1) {
+                increases.push(replay.tick());
+            }
+        }
+        assert_eq!(increases, [Some(2)]);
+    }
+
+    #[test]
+    fn replays_read_gaps_like_the_live_run_under_every_policy() {
+        let base = Address::new(0x1000);
+        let values = [
+            Some(1u32),
+            Some(2),
+            None,
+            None,
+            Some(3),
+            Some(4),
+            None,
+            Some(4),
+        ];
+
+        for policy in [
+            StalePolicy::Hold,
+            StalePolicy::Invalidate,
+            StalePolicy::SuppressNextTransition,
+        ] {
+            let mut memory = FakeMemory::new();
+            let mut watcher = MemoryWatcher::<u32>::new(0x10);
+            watcher.set_stale_policy(policy);
+            let mut recorder = TraceRecorder::new();
+            let mut trace = String::new();
+            let mut live = Vec::new();
+
+            for (tick, value) in values.into_iter().enumerate() {
+                match value {
+                    Some(value) => memory.write(0x1010u64, value),
+                    None => memory.unmap(0x1010u64, 4),
+                }
+                watcher.update(&memory, base);
+                if watcher.increased_by(1) {
+                    live.push(Some(tick as u64));
+                }
+                recorder.record("progress", &watcher);
+                recorder.finish_tick(|line| {
+                    trace.push_str(line);
+                    trace.push('\n');
+                });
+            }
+
+            let mut replay = TraceReplay::parse(&trace).unwrap();
+            let mut replayed = MemoryWatcher::<u32>::new(0x10);
+            replayed.set_stale_policy(policy);
+            let mut increases = Vec::new();
+            while replay.advance() {
+                replay.update("progress", &mut replayed);
+                if replayed.increased_by(1) {
+                    increases.push(replay.tick());
+                }
+            }
+            assert_eq!(increases, live, "{policy:?}");
+        }
+    }
+
+    #[test]
+    fn reports_the_line_of_a_malformed_event() {
+        assert_eq!(
+            TraceReplay::parse("log output\n{\"tick\":1,\"name\":\"x\",\"value\":\"0\"}").err(),
+            Some(TraceError { line: 2 })
+        );
+        assert_eq!(
+            TraceEvent::parse("{\"tick\":5,\"name\":\"loading\",\"value\":null}"),
+            Some(TraceEvent {
+                tick: 5,
+                name: "loading".into(),
+                value: None,
+            })
+        );
+    }
+
+    #[test]
+    fn escapes_names_that_are_not_plain_json() {
+        let name = "cutscene \"TSSP\"\\intro\n";
+        let line = format_event(3, name, Some(&[1]));
+        assert_eq!(
+            line,
+            "{\"tick\":3,\"name\":\"cutscene \\\"TSSP\\\"\\\\intro\\u000a\",\"value\":\"01\"}"
+        );
+        assert_eq!(
+            TraceEvent::parse(&line),
+            Some(TraceEvent {
+                tick: 3,
+                name: name.into(),
+                value: Some([1].into()),
+            })
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use alloc::{collections::BTreeMap, string::String};
+use core::fmt::{Display, Write};
+
+use crate::Timer;
+
+/// Publishes values as timer variables, so layouts can show them in text
+/// components.
+///
+/// A variable is only sent to the timer when its value changed since it was
+/// last published.
+#[derive(Default)]
+pub struct Variables {
+    published: BTreeMap<&'static str, String>,
+    buf: String,
+}
+
+impl Variables {
+    /// Creates a publisher that hasn't published anything yet.
+    pub const fn new() -> Self {
+        Self {
+            published: BTreeMap::new(),
+            buf: String::new(),
+        }
+    }
+
+    /// Publishes `value` as the variable `key`, unless it already has that
+    /// value.
+    pub fn set(&mut self, timer: &mut impl Timer, key: &'static str, value: impl Display) {
+        self.buf.clear();
+        let _ = write!(self.buf, "{value}");
+
+        match self.published.get_mut(key) {
+            Some(published) if *published == self.buf => {}
+            Some(published) => {
+                published.clone_from(&self.buf);
+                timer.set_variable(key, published);
+            }
+            None => {
+                timer.set_variable(key, &self.buf);
+                self.published.insert(key, self.buf.clone());
+            }
+        }
+    }
+
+    /// Forgets what was published, so every variable is sent again the next
+    /// time it's set.
+    pub fn clear(&mut self) {
+        self.published.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RecordingTimer, TimerAction};
+
+    #[test]
+    fn publishes_only_changes() {
+        let mut variables = Variables::new();
+        let mut timer = RecordingTimer::new();
+
+        for packages in [47, 47, 48] {
+            variables.set(
+                &mut timer,
+                "Hidden Packages",
+                format_args!("{packages}/100"),
+            );
+            variables.set(&mut timer, "Rampages", 3);
+        }
+        variables.clear();
+        variables.set(&mut timer, "Rampages", 3);
+
+        let set = |key: &str, value: &str| TimerAction::SetVariable {
+            key: key.into(),
+            value: value.into(),
+        };
+        assert_eq!(
+            timer.take_actions(),
+            [
+                set("Hidden Packages", "47/100"),
+                set("Rampages", "3"),
+                set("Hidden Packages", "48/100"),
+                set("Rampages", "3"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Telling game versions apart.
+//!
+//! A game lists its versions together with the probes that identify them, and
+//! [`detect`] returns the first version whose probes all match:
+//!
+//! ```
+//! use autosplitter_helpers::version::Probe;
+//!
+//! #[derive(Copy, Clone)]
+//! enum Version {
+//!     Steam,
+//!     Retail,
+//! }
+//!
+//! const VERSIONS: &[(Version, &[Probe])] = &[
+//!     (Version::Steam, &[Probe::ImageSize(0x5E9000)]),
+//!     (
+//!         Version::Retail,
+//!         &[Probe::U32 {
+//!             offset: 0x1C1E70,
+//!             value: 0x53E58955,
+//!         }],
+//!     ),
+//! ];
+//! ```
+
+use alloc::{format, string::String};
+use asr::Address;
+use core::fmt;
+
+use crate::{pe, MemorySource};
+
+/// Something about a module that identifies a version.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Probe {
+    /// The `SizeOfImage` in the PE header.
+    ImageSize(u32),
+    /// The `TimeDateStamp` in the PE header.
+    Timestamp(u32),
+    /// The file version in the version resource.
+    FileVersion([u16; 4]),
+    /// The product version in the version resource.
+    ProductVersion([u16; 4]),
+    /// The CRC-32 of a section's bytes in memory.
+    SectionChecksum {
+        section: &'static str,
+        checksum: u32,
+    },
+    /// A 32-bit value at an offset from the module's base address.
+    U32 { offset: u64, value: u32 },
+    /// Bytes at an offset from the module's base address.
+    Bytes { offset: u64, bytes: &'static [u8] },
+}
+
+impl Probe {
+    /// Returns whether the module at `module` matches the probe.
+    pub fn matches<S: MemorySource + ?Sized>(&self, memory: &S, module: Address) -> bool {
+        match *self {
+            Self::ImageSize(size) => pe::read_size_of_image(memory, module) == Some(size),
+            Self::Timestamp(timestamp) => pe::read_timestamp(memory, module) == Some(timestamp),
+            Self::FileVersion(version) => pe::read_file_version(memory, module) == Some(version),
+            Self::ProductVersion(version) => {
+                pe::read_product_version(memory, module) == Some(version)
+            }
+            Self::SectionChecksum { section, checksum } => {
+                pe::read_section_checksum(memory, module, section) == Some(checksum)
+            }
+            Self::U32 { offset, value } => memory.read::<u32>(module + offset) == Some(value),
+            Self::Bytes { offset, bytes } => {
+                let mut buf = alloc::vec![0; bytes.len()];
+                memory.read_bytes(module + offset, &mut buf).is_some() && buf == bytes
+            }
+        }
+    }
+}
+
+/// The identifiers of a module that didn't match any known version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Unsupported {
+    pub image_size: Option<u32>,
+    pub timestamp: Option<u32>,
+    pub file_version: Option<[u16; 4]>,
+    pub product_version: Option<[u16; 4]>,
+    /// The checksum of the `.text` section.
+    pub code_checksum: Option<u32>,
+}
+
+impl Unsupported {
+    /// Reads the identifiers of the module at `module`, to report why
+    /// [`detect`] found no version. This checksums the module's code, so only
+    /// call it once detection has failed for good.
+    pub fn read<S: MemorySource + ?Sized>(memory: &S, module: Address) -> Self {
+        Self {
+            image_size: pe::read_size_of_image(memory, module),
+            timestamp: pe::read_timestamp(memory, module),
+            file_version: pe::read_file_version(memory, module),
+            product_version: pe::read_product_version(memory, module),
+            code_checksum: pe::read_section_checksum(memory, module, ".text"),
+        }
+    }
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn hex(value: Option<u32>) -> String {
+            value.map_or_else(|| "?".into(), |value| format!("0x{value:X}"))
+        }
+
+        write!(
+            f,
+            "unsupported version: size={}, timestamp={}, .text crc={}",
+            hex(self.image_size),
+            hex(self.timestamp),
+            hex(self.code_checksum),
+        )?;
+        if let Some([a, b, c, d]) = self.file_version {
+            write!(f, ", file version={a}.{b}.{c}.{d}")?;
+        }
+        if let Some([a, b, c, d]) = self.product_version {
+            write!(f, ", product version={a}.{b}.{c}.{d}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the first version whose probes all match the module at `module`.
+/// [`Unsupported::read`] tells why none did.
+pub fn detect<V: Copy, S: MemorySource + ?Sized>(
+    memory: &S,
+    module: Address,
+    versions: &[(V, &[Probe])],
+) -> Option<V> {
+    versions
+        .iter()
+        .find(|(_, probes)| probes.iter().all(|probe| probe.matches(memory, module)))
+        .map(|&(version, _)| version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pe::tests::write_module, FakeMemory};
+    use alloc::string::ToString;
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Version {
+        Steam,
+        V11,
+        Patch4,
+    }
+
+    const VERSIONS: &[(Version, &[Probe])] = &[
+        (Version::Steam, &[Probe::ImageSize(0x5E9000)]),
+        (
+            Version::V11,
+            &[
+                Probe::Timestamp(0x3C8B2F1A),
+                Probe::Bytes {
+                    offset: 0x1000,
+                    bytes: &[0x10],
+                },
+            ],
+        ),
+        (Version::Patch4, &[Probe::FileVersion([1, 0, 4, 0])]),
+    ];
+
+    #[test]
+    fn detects_the_first_version_whose_probes_all_match() {
+        let mut memory = FakeMemory::new();
+        let module = Address::new(0x400000);
+
+        write_module(&mut memory, 0x400000, 0x5E9000, 0, [1, 0, 0, 0]);
+        assert_eq!(detect(&memory, module, VERSIONS), Some(Version::Steam));
+
+        write_module(&mut memory, 0x400000, 0x5E8000, 0x3C8B2F1A, [1, 0, 4, 0]);
+        assert_eq!(detect(&memory, module, VERSIONS), Some(Version::Patch4));
+        memory.write(0x401000u64, 0x10u8);
+        assert_eq!(detect(&memory, module, VERSIONS), Some(Version::V11));
+
+        write_module(&mut memory, 0x400000, 0x5E8000, 0x1234, [1, 0, 7, 0]);
+        assert_eq!(detect(&memory, module, VERSIONS), None);
+        assert_eq!(
+            Unsupported::read(&memory, module).to_string(),
+            "unsupported version: size=0x5E8000, timestamp=0x1234, .text crc=?, \
+             file version=1.0.7.0, product version=1.0.7.0"
+        );
+    }
+}
@@ -14,8 +14,6 @@ pub fn mission_start_text(key: &str, version: Version) -> Option<&'static str> {
         }
     }
 
-    asr::print_message(key);
-
     // Look up the base text from the table
     MISSION_TEXT
         .iter()
@@ -3,6 +3,8 @@ extern crate alloc;
 
 pub mod helpers;
 pub mod missions;
+pub mod route;
+pub mod rules;
 pub mod settings;
 pub mod split_guard;
 pub mod version;
@@ -11,184 +13,286 @@ pub mod watchers;
 #[global_allocator]
 static ALLOC: dlmalloc::GlobalDlmalloc = dlmalloc::GlobalDlmalloc;
 
-use alloc::{
-    format,
-    string::{String, ToString},
-};
+use alloc::{format, string::String};
 use asr::{
     future::next_tick,
-    settings::Map,
-    timer::{self, TimerState},
-    Process,
+    settings::{Gui, Map},
+    timer::TimerState,
+};
+use autosplitter_helpers::{
+    attach::{attach_any, detect_version, ProcessCandidate},
+    log,
+    rules::SettingSource,
+    ActionHistory, AsrTimer, Timer, Variables, WatcherKey,
 };
 
 use crate::{
     helpers::mission_start_text,
-    missions::{COLLECTIBLES, MISSIONS},
-    settings::{register_settings, setting_enabled},
-    split_guard::{SplitGuard, MAX_COLLECTIBLE},
-    version::Version,
+    missions::{Mission, COLLECTIBLES},
+    route::SplitFilter,
+    rules::Rules,
+    settings::{register_settings, setting_enabled, DebugSettings},
+    split_guard::{SplitGuard, SplitKey},
+    version::{Version, VERSIONS},
     watchers::Watchers,
 };
 
 asr::async_main!(stable);
 asr::panic_handler!();
 
-const PROCESS_NAME: &str = "gta3.exe";
+const PROCESSES: &[ProcessCandidate] = &[ProcessCandidate::new("gta3.exe")];
 
 async fn main() {
     register_settings();
+    let mut debug_settings = DebugSettings::register();
+
+    let mut history = ActionHistory::new("gta3");
 
     loop {
-        let process = Process::wait_attach(PROCESS_NAME).await;
+        let (process, candidate) = attach_any(PROCESSES).await;
         process
             .until_closes(async {
-                if let Ok(base_address) = process.get_module_address(PROCESS_NAME) {
-                    if let Some(version) = Version::detect(&process, base_address) {
-                        let mut watchers = Watchers::new(version);
-                        let mut split_guard = SplitGuard::new();
-
-                        // JP shifts the gameState sentinel values by 4.
-                        let gs_shift: u32 = if version == Version::Japanese { 4 } else { 0 };
-
-                        loop {
-                            watchers.update(&process, base_address);
-
-                            let settings_map = Map::load();
-                            let timer_state = timer::state();
-
-                            if let Some(gs) = watchers.game_state.pair() {
-                                if setting_enabled(&settings_map, "timer_start", true)
-                                    && gs.old == 8 + gs_shift
-                                    && gs.current == 9 + gs_shift
-                                    && timer_state == TimerState::NotRunning
-                                {
-                                    timer::start();
-                                    split_guard.clear();
-                                }
-
-                                if setting_enabled(&settings_map, "timer_reset", true)
-                                    && gs.old == 9 + gs_shift
-                                    && gs.current == 8 + gs_shift
-                                {
-                                    timer::reset();
-                                }
-                            }
-
-                            if timer_state == TimerState::Running {
-                                for (i, &(complete_key, _, _)) in MISSIONS.iter().enumerate() {
-                                    if split_guard.missions_complete[i] {
-                                        continue;
-                                    }
-                                    if !setting_enabled(&settings_map, complete_key, true) {
-                                        continue;
-                                    }
-                                    if let Some(p) = watchers.missions[complete_key].pair() {
-                                        if p.current > p.old {
-                                            split_guard.missions_complete[i] = true;
-                                            timer::split();
-                                        }
-                                    }
-                                }
-
-                                if let Some(text_pair) = watchers.mission_text.pair() {
-                                    if text_pair.current != text_pair.old {
-                                        let current_text =
-                                            String::from_utf16(text_pair.current.as_slice())
-                                                .unwrap();
-
-                                        for (i, &(complete_key, _, _)) in
-                                            MISSIONS.iter().enumerate()
-                                        {
-                                            let start_key = format!("{complete_key}_start");
-
-                                            if split_guard.missions_start[i] {
-                                                continue;
-                                            }
-                                            if !setting_enabled(
-                                                &settings_map,
-                                                &start_key.to_string(),
-                                                false,
-                                            ) {
-                                                continue;
-                                            }
-
-                                            if let Some(expected) =
-                                                mission_start_text(complete_key, version)
-                                            {
-                                                let matches = if version == Version::Japanese {
-                                                    // JP: bare uppercase, e.g. "LUIGI'S GIRLS"
-                                                    current_text == expected
-                                                } else {
-                                                    let quoted = format!("'{expected}'");
-                                                    current_text == quoted.as_str()
-                                                };
-
-                                                if matches {
-                                                    split_guard.missions_start[i] = true;
-                                                    timer::split();
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-
-                                for (i, &(key, _, _, max)) in COLLECTIBLES.iter().enumerate() {
-                                    if let Some(p) = watchers.collectibles[key].pair() {
-                                        if p.current <= p.old {
-                                            continue;
-                                        }
-
-                                        let key_all = format!("{key}_all");
-                                        let key_each = format!("{key}_each");
-
-                                        if setting_enabled(&settings_map, key_all.as_str(), false)
-                                            && !split_guard.collectibles_all[i]
-                                            && p.current == max
-                                            && p.old == max - 1
-                                        {
-                                            split_guard.collectibles_all[i] = true;
-                                            timer::split();
-                                        }
-
-                                        if setting_enabled(&settings_map, key_each.as_str(), false)
-                                        {
-                                            let idx = (p.current as usize).saturating_sub(1);
-                                            if idx < MAX_COLLECTIBLE
-                                                && !split_guard.collectibles_each[i][idx]
-                                            {
-                                                split_guard.collectibles_each[i][idx] = true;
-                                                timer::split();
-                                            }
-                                        }
-                                    }
-                                }
-
-                                if setting_enabled(&settings_map, "btg_final_split", true) {
-                                    if let (Some(hp), Some(tm)) =
-                                        (watchers.te_helipad.pair(), watchers.te_timer.pair())
-                                    {
-                                        if hp.current == 1 && tm.current != tm.old {
-                                            timer::split();
-                                        }
-                                    }
-                                }
-
-                                if setting_enabled(&settings_map, "hundo_final_split", false) {
-                                    if let Some(progress_made) = watchers.progress_made.pair() {
-                                        if progress_made.current == 154 && progress_made.old != 154
-                                        {
-                                            timer::split();
-                                        }
-                                    }
-                                }
-                            }
-
-                            next_tick().await;
-                        }
+                let Some((base_address, version)) =
+                    detect_version(&process, candidate.module, VERSIONS, &mut AsrTimer).await
+                else {
+                    loop {
+                        next_tick().await;
+                    }
+                };
+
+                let mut watchers = Watchers::new(version);
+                let mut split_guard = SplitGuard::new();
+                let mut rules = Rules::new();
+                let mut split_filter = SplitFilter::new();
+                let mut variables = Variables::new();
+
+                loop {
+                    watchers.update(&process, base_address);
+
+                    let settings_map = Map::load();
+                    debug_settings.update_from(&settings_map);
+                    split_filter.load_route(&settings_map);
+                    log::set_level(debug_settings.log_level.into());
+                    if setting_enabled(&settings_map, "expose_variables", false) {
+                        publish_variables(&mut AsrTimer, &mut variables, &watchers);
                     }
+                    tick(
+                        &mut history.timer(&mut AsrTimer),
+                        &watchers,
+                        &mut split_guard,
+                        &mut rules,
+                        &mut split_filter,
+                        &settings_map,
+                        version,
+                    );
+
+                    next_tick().await;
                 }
             })
             .await;
     }
 }
+
+/// Publishes completion progress and collectible counts as timer variables.
+fn publish_variables(timer: &mut impl Timer, variables: &mut Variables, watchers: &Watchers) {
+    if let Some(progress) = watchers.values[watchers.progress_made].pair() {
+        variables.set(timer, "Progress", format_args!("{}/154", progress.current));
+    }
+
+    for &(key, title, _, total) in COLLECTIBLES {
+        if let Some(count) = watchers.collectibles.get(key).and_then(|w| w.pair()) {
+            variables.set(timer, title, format_args!("{}/{total}", count.current));
+        }
+    }
+}
+
+/// Starts and resets on game state changes and splits for missions,
+/// collectibles and the final splits.
+fn tick(
+    timer: &mut impl Timer,
+    watchers: &Watchers,
+    split_guard: &mut SplitGuard,
+    rules: &mut Rules,
+    split_filter: &mut SplitFilter,
+    settings: &impl SettingSource,
+    version: Version,
+) {
+    let timer_state = timer.state();
+
+    split_guard.sync(timer.current_split_index());
+    rules.sync(timer.current_split_index());
+    split_filter.update(settings, timer.current_split_index());
+
+    let game_state = &watchers.values[watchers.game_state];
+    let game_state_changed = |old: u32, current: u32| game_state.changed_from_to(&old, &current);
+
+    // JP shifts the gameState sentinel values by 4.
+    let gs_shift: u32 = if version == Version::Japanese { 4 } else { 0 };
+
+    if setting_enabled(settings, "timer_start", true)
+        && game_state_changed(8 + gs_shift, 9 + gs_shift)
+        && timer_state == TimerState::NotRunning
+    {
+        timer.start_because(format_args!("game state {}→{}", 8 + gs_shift, 9 + gs_shift));
+        split_guard.clear();
+        rules.clear();
+    }
+
+    if setting_enabled(settings, "timer_reset", true)
+        && game_state_changed(9 + gs_shift, 8 + gs_shift)
+    {
+        timer.reset_because(format_args!("game state {}→{}", 9 + gs_shift, 8 + gs_shift));
+    }
+
+    if timer_state != TimerState::Running {
+        return;
+    }
+
+    // In route order, find out which events happen this tick before any of
+    // them splits.
+    if split_filter.is_ordered() {
+        check_splits(timer, watchers, split_guard, rules, version, |key, _| {
+            split_filter.collect(key);
+            false
+        });
+        split_filter.plan();
+    }
+    check_splits(
+        timer,
+        watchers,
+        split_guard,
+        rules,
+        version,
+        |key, default| split_filter.allows(settings, key, default),
+    );
+}
+
+/// Splits for every event of this tick that `accept` allows, given the
+/// event's settings key and whether the setting defaults to enabled.
+fn check_splits(
+    timer: &mut impl Timer,
+    watchers: &Watchers,
+    split_guard: &mut SplitGuard,
+    rules: &mut Rules,
+    version: Version,
+    mut accept: impl FnMut(&str, bool) -> bool,
+) {
+    rules
+        .missions
+        .evaluate_with(&watchers.missions, timer, |rule| {
+            accept(&rule.setting, rule.default)
+        });
+
+    let mission_text = &watchers.values[watchers.mission_text];
+    if let Some(text_pair) = mission_text.pair().filter(|_| mission_text.changed()) {
+        let current_text = String::from_utf16_lossy(text_pair.current.as_slice());
+
+        for &mission in Mission::ALL {
+            let complete_key = mission.name();
+            let start_key = format!("{complete_key}_start");
+
+            if split_guard.is_done(&SplitKey::MissionStart(mission)) {
+                continue;
+            }
+
+            if let Some(expected) = mission_start_text(complete_key, version) {
+                let matches = if version == Version::Japanese {
+                    // JP: bare uppercase, e.g. "LUIGI'S GIRLS"
+                    current_text == expected
+                } else {
+                    let quoted = format!("'{expected}'");
+                    current_text == quoted.as_str()
+                };
+
+                if matches && accept(&start_key, false) {
+                    split_guard.try_split(
+                        SplitKey::MissionStart(mission),
+                        timer,
+                        format_args!("{start_key}"),
+                    );
+                }
+            }
+        }
+    }
+
+    rules
+        .collectibles
+        .evaluate_with(&watchers.collectibles, timer, |rule| {
+            accept(&rule.setting, rule.default)
+        });
+
+    if let Some(hp) = watchers.values[watchers.te_helipad].pair() {
+        if hp.current == 1
+            && watchers.values[watchers.te_timer].changed()
+            && accept("btg_final_split", true)
+        {
+            timer.split_because(format_args!("btg_final_split"));
+        }
+    }
+
+    if watchers.values[watchers.progress_made].changed_to(&154)
+        && accept("hundo_final_split", false)
+    {
+        timer.split_because(format_args!("hundo_final_split"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::BTreeMap;
+    use asr::Address;
+    use autosplitter_helpers::{FakeMemory, RecordingTimer, TimerAction};
+
+    const BASE: u64 = 0x400000;
+
+    #[test]
+    fn starts_and_splits_on_scripted_steam_memory() {
+        let mut memory = FakeMemory::new();
+        let mut watchers = Watchers::new(Version::Steam);
+        let mut split_guard = SplitGuard::new();
+        let mut rules = Rules::new();
+        let mut split_filter = SplitFilter::new();
+        let settings = BTreeMap::<&str, bool>::new();
+        let mut timer = RecordingTimer::new();
+
+        memory.write_bytes(BASE + 0x274F20, &[0; 256]);
+        memory.write(BASE + 0x50651C, 0u32);
+        memory.write(BASE + 0x35F6B8, 0u8);
+        memory.write(BASE + 0x35BA2C, 0u32);
+        memory.write(BASE + Mission::ALL[0].address(), 0u32);
+
+        let mut run = |memory: &FakeMemory, timer: &mut RecordingTimer| {
+            watchers.update(memory, Address::new(BASE));
+            tick(
+                timer,
+                &watchers,
+                &mut split_guard,
+                &mut rules,
+                &mut split_filter,
+                &settings,
+                Version::Steam,
+            );
+            timer.take_actions()
+        };
+
+        memory.write(BASE + 0x505A2C, 8u32);
+        assert_eq!(run(&memory, &mut timer), []);
+        memory.write(BASE + 0x505A2C, 9u32);
+        assert_eq!(run(&memory, &mut timer), [TimerAction::Start]);
+
+        // Luigi's Girls is passed, and only splits once.
+        memory.write(BASE + Mission::ALL[0].address(), 1u32);
+        assert_eq!(run(&memory, &mut timer), [TimerAction::Split]);
+        assert_eq!(run(&memory, &mut timer), []);
+
+        // A torn read of the mission text isn't valid UTF-16.
+        memory.write(BASE + 0x274F20, 0xD800u16);
+        assert_eq!(run(&memory, &mut timer), []);
+
+        // The helipad cutscene of The Exchange starts its timer.
+        memory.write(BASE + 0x35F6B8, 1u8);
+        memory.write(BASE + 0x35BA2C, 100u32);
+        assert_eq!(run(&memory, &mut timer), [TimerAction::Split]);
+    }
+}
@@ -1,3 +1,5 @@
+use autosplitter_helpers::WatcherKey;
+
 pub const MISSIONS: &[(&str, &str, u64)] = &[
     ("luigis_girls", "Luigi's Girls", 0x35B75C),
     (
@@ -94,6 +96,37 @@ pub const MISSIONS: &[(&str, &str, u64)] = &[
     ("gripped", "Gripped", 0x35B978),
 ];
 
+/// A mission in [`MISSIONS`], used as the key of its completion watcher.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Mission(usize);
+
+impl Mission {
+    /// Returns the address of the mission's completion counter.
+    pub fn address(self) -> u64 {
+        MISSIONS[self.0].2
+    }
+}
+
+impl WatcherKey for Mission {
+    const ALL: &'static [Self] = &{
+        let mut all = [Mission(0); MISSIONS.len()];
+        let mut i = 0;
+        while i < all.len() {
+            all[i] = Mission(i);
+            i += 1;
+        }
+        all
+    };
+
+    fn index(self) -> usize {
+        self.0
+    }
+
+    fn name(self) -> &'static str {
+        MISSIONS[self.0].0
+    }
+}
+
 pub const COLLECTIBLES: &[(&str, &str, u64, u32)] = &[
     ("hidden_packages", "Hidden Packages", 0x35C3D4, 100),
     ("rampages", "Rampages", 0x35C0AC, 20),
@@ -0,0 +1,194 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use asr::settings::Map;
+use autosplitter_helpers::{info, rules::SettingSource, Route, RouteCheck};
+
+use crate::settings::setting_enabled;
+
+/// Decides which splits fire: every enabled one, or in route order only the
+/// next ones listed in the `route` setting.
+///
+/// In route order, the events of a tick are [collected](Self::collect) before
+/// any of them splits, so events that happen in the same tick split in route
+/// order rather than in the order they are checked in.
+pub struct SplitFilter {
+    route: Route,
+    ordered: bool,
+    split_index: Option<u64>,
+    log_out_of_order: bool,
+    /// The keys of this tick's events.
+    events: Vec<String>,
+    /// The keys of this tick's events that are next in the route.
+    next: Vec<String>,
+    /// The last out-of-order event that was logged and the split index it
+    /// happened at, so events that repeat every tick are logged once.
+    logged: Option<(String, Option<u64>)>,
+}
+
+impl SplitFilter {
+    pub fn new() -> Self {
+        Self {
+            route: Route::default(),
+            ordered: false,
+            split_index: None,
+            log_out_of_order: false,
+            events: Vec::new(),
+            next: Vec::new(),
+            logged: None,
+        }
+    }
+
+    /// Reads the `route` setting.
+    pub fn load_route(&mut self, settings_map: &Map) {
+        self.route = Route::load(settings_map, "route").unwrap_or_default();
+    }
+
+    /// Reads the route settings. Call this every tick before checking splits.
+    pub fn update(&mut self, settings: &impl SettingSource, split_index: Option<u64>) {
+        self.ordered = setting_enabled(settings, "route_ordered", false);
+        self.split_index = split_index;
+        self.log_out_of_order = setting_enabled(settings, "route_log_out_of_order", false);
+        self.events.clear();
+        self.next.clear();
+    }
+
+    /// Returns whether splits only fire in route order, in which case the
+    /// tick's events need to be collected and planned before checking them.
+    pub fn is_ordered(&self) -> bool {
+        self.ordered
+    }
+
+    /// Notes that the split with the settings key `key` would fire this tick.
+    pub fn collect(&mut self, key: &str) {
+        self.events.push(key.to_string());
+    }
+
+    /// Works out which of the collected events split: starting at the
+    /// timer's split index, as long as the next key of the route is one of
+    /// them. The others are out of order.
+    pub fn plan(&mut self) {
+        let mut split_index = self.split_index;
+        while let Some(position) = self
+            .events
+            .iter()
+            .position(|key| self.route.expected(split_index) == Some(key.as_str()))
+        {
+            self.next.push(self.events.swap_remove(position));
+            split_index = split_index.map(|index| index + 1);
+        }
+
+        for key in self.events.drain(..) {
+            let RouteCheck::OutOfOrder { expected } = self.route.check(&key, split_index) else {
+                continue;
+            };
+            let event = (key, split_index);
+            if self.log_out_of_order && self.logged.as_ref() != Some(&event) {
+                info!(
+                    "out of route order: {}, expected {}",
+                    event.0,
+                    expected.unwrap_or("nothing")
+                );
+                self.logged = Some(event);
+            }
+        }
+    }
+
+    /// Returns whether the split with the settings key `key` may fire now.
+    /// In route order, that is whether [`plan`](Self::plan) found it to be
+    /// next.
+    pub fn allows(&mut self, settings: &impl SettingSource, key: &str, default: bool) -> bool {
+        if !self.ordered {
+            return setting_enabled(settings, key, default);
+        }
+
+        match self.next.iter().position(|next| next == key) {
+            Some(position) => {
+                self.next.remove(position);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Default for SplitFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::BTreeMap;
+
+    fn split_in_tick(
+        split_filter: &mut SplitFilter,
+        settings: &BTreeMap<&str, bool>,
+        split_index: u64,
+        events: &[&str],
+    ) -> Vec<bool> {
+        split_filter.update(settings, Some(split_index));
+        for key in events {
+            split_filter.collect(key);
+        }
+        split_filter.plan();
+        events
+            .iter()
+            .map(|key| split_filter.allows(settings, key, false))
+            .collect()
+    }
+
+    #[test]
+    fn splits_events_of_one_tick_in_route_order() {
+        let settings = BTreeMap::from([("route_ordered", true)]);
+        let mut split_filter = SplitFilter::new();
+        split_filter.route = Route::parse(
+            "luigis_girls, hidden_packages_each, hidden_packages_all, btg_final_split",
+        );
+
+        // The 100th package is checked for "all" before "each".
+        assert_eq!(
+            split_in_tick(
+                &mut split_filter,
+                &settings,
+                1,
+                &["hidden_packages_all", "hidden_packages_each"]
+            ),
+            [true, true]
+        );
+        assert_eq!(
+            split_in_tick(&mut split_filter, &settings, 0, &["hidden_packages_each"]),
+            [false]
+        );
+        assert_eq!(
+            split_in_tick(
+                &mut split_filter,
+                &settings,
+                0,
+                &["hidden_packages_all", "luigis_girls"]
+            ),
+            [false, true]
+        );
+
+        // The runner undid the last split.
+        assert_eq!(
+            split_in_tick(&mut split_filter, &settings, 1, &["hidden_packages_each"]),
+            [true]
+        );
+    }
+
+    #[test]
+    fn splits_every_enabled_event_outside_of_route_order() {
+        let settings = BTreeMap::from([("hidden_packages_each", true)]);
+        let mut split_filter = SplitFilter::new();
+        split_filter.route = Route::parse("luigis_girls");
+
+        split_filter.update(&settings, Some(0));
+        assert!(!split_filter.is_ordered());
+        assert!(split_filter.allows(&settings, "hidden_packages_each", false));
+        assert!(!split_filter.allows(&settings, "luigis_girls", false));
+    }
+}
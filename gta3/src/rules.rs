@@ -0,0 +1,67 @@
+use alloc::format;
+use autosplitter_helpers::{
+    rules::{Frequency, Predicate, Rule, RuleSet},
+    WatcherKey,
+};
+
+use crate::missions::{Mission, COLLECTIBLES};
+
+/// The counter splits, evaluated against the mission and collectible watchers.
+pub struct Rules {
+    pub missions: RuleSet<u32, Mission>,
+    pub collectibles: RuleSet<u32>,
+}
+
+impl Rules {
+    pub fn new() -> Self {
+        let mut missions = RuleSet::new().with_name("mission complete");
+        for &mission in Mission::ALL {
+            missions.push(Rule {
+                watcher: mission,
+                predicate: Predicate::Increased,
+                setting: mission.name().into(),
+                default: true,
+                frequency: Frequency::Once,
+            });
+        }
+
+        let mut collectibles = RuleSet::new().with_name("collectible");
+        for &(key, _, _, max) in COLLECTIBLES {
+            collectibles.push(Rule {
+                watcher: key,
+                predicate: Predicate::ChangedFromTo(max - 1, max),
+                setting: format!("{key}_all"),
+                default: false,
+                frequency: Frequency::Once,
+            });
+            collectibles.push(Rule {
+                watcher: key,
+                predicate: Predicate::Increased,
+                setting: format!("{key}_each"),
+                default: false,
+                frequency: Frequency::EachValue,
+            });
+        }
+
+        Self {
+            missions,
+            collectibles,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.missions.clear();
+        self.collectibles.clear();
+    }
+
+    pub fn sync(&mut self, current_split_index: Option<u64>) {
+        self.missions.sync(current_split_index);
+        self.collectibles.sync(current_split_index);
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self::new()
+    }
+}
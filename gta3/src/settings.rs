@@ -1,12 +1,22 @@
-use crate::{COLLECTIBLES, MISSIONS};
+use crate::missions::{COLLECTIBLES, MISSIONS};
 use alloc::format;
-use asr::settings::gui::{add_bool, add_title, set_tooltip};
-use asr::settings::Map;
+use asr::settings::gui::{add_bool, add_title, set_tooltip, Title};
+use asr::settings::Gui;
+use autosplitter_helpers::{rules::SettingSource, settings::LogLevel};
 
 pub fn register_settings() {
     // Timer controls
     add_bool("timer_start", "Start timer automatically", true);
     add_bool("timer_reset", "Reset timer automatically", true);
+    add_bool(
+        "expose_variables",
+        "Publish game stats as timer variables",
+        false,
+    );
+    set_tooltip(
+        "expose_variables",
+        "Shows progress and collectible counts in text components.",
+    );
 
     // Mission end splits
     add_title("title_missions_complete", "Missions (complete)", 0);
@@ -48,8 +58,33 @@ pub fn register_settings() {
         "hundo_final_split",
         "Splits once you reach 100% game completion.",
     );
+
+    // Route
+    add_title("title_route", "Route", 0);
+    add_bool("route_ordered", "Only split in route order", false);
+    set_tooltip(
+        "route_ordered",
+        "Ignores the split settings above and only splits when the next event \
+         of the route happens. The route is the \"route\" setting in the \
+         layout: the setting keys of the splits in order, one per split, e.g. \
+         \"luigis_girls, hidden_packages_each, btg_final_split\".",
+    );
+    add_bool(
+        "route_log_out_of_order",
+        "Log events that are out of route order",
+        false,
+    );
+}
+
+/// The settings after the ones [`register_settings`] adds.
+#[derive(Gui)]
+pub struct DebugSettings {
+    /// Debug
+    _debug: Title,
+    /// Debug log level
+    pub log_level: LogLevel,
 }
 
-pub fn setting_enabled(map: &Map, key: &str, default: bool) -> bool {
-    map.get(key).and_then(|v| v.get_bool()).unwrap_or(default)
+pub fn setting_enabled(settings: &impl SettingSource, key: &str, default: bool) -> bool {
+    settings.get_bool(key).unwrap_or(default)
 }
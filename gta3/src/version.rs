@@ -1,6 +1,6 @@
-use asr::{file_format::pe::read_size_of_image, Address, Process};
+use autosplitter_helpers::version::Probe;
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Version {
     V10,
     V11,
@@ -8,6 +8,34 @@ pub enum Version {
     Japanese,
 }
 
+const VERSION_CHECK_NUMBER: u32 = 1407551829;
+
+pub const VERSIONS: &[(Version, &[Probe])] = &[
+    (Version::Steam, &[Probe::ImageSize(6197248)]),
+    (Version::Steam, &[Probe::ImageSize(5836800)]),
+    (
+        Version::V10,
+        &[Probe::U32 {
+            offset: 0x1C1E70,
+            value: VERSION_CHECK_NUMBER,
+        }],
+    ),
+    (
+        Version::V11,
+        &[Probe::U32 {
+            offset: 0x1C2130,
+            value: VERSION_CHECK_NUMBER,
+        }],
+    ),
+    (
+        Version::Japanese,
+        &[Probe::U32 {
+            offset: 0x1B52D0,
+            value: VERSION_CHECK_NUMBER,
+        }],
+    ),
+];
+
 impl Version {
     pub fn offset(self) -> i64 {
         match self {
@@ -16,27 +44,4 @@ impl Version {
             Version::Japanese => -0x21E0,
         }
     }
-
-    pub fn detect(process: &Process, base_address: Address) -> Option<Self> {
-        let module_size = read_size_of_image(process, base_address).unwrap();
-        match module_size {
-            6197248 | 5836800 => return Some(Version::Steam),
-            _ => {}
-        }
-
-        const VERSION_CHECK_NUMBER: u32 = 1407551829;
-        let base = process.get_module_address("gta3.exe").ok()?;
-
-        if process.read::<u32>(base + 0x1C1E70u64).ok()? == VERSION_CHECK_NUMBER {
-            return Some(Version::V10);
-        }
-        if process.read::<u32>(base + 0x1C2130u64).ok()? == VERSION_CHECK_NUMBER {
-            return Some(Version::V11);
-        }
-        if process.read::<u32>(base + 0x1B52D0u64).ok()? == VERSION_CHECK_NUMBER {
-            return Some(Version::Japanese);
-        }
-
-        None
-    }
 }
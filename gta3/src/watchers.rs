@@ -1,15 +1,21 @@
-use crate::{version::Version, COLLECTIBLES, MISSIONS};
-use asr::{string::ArrayWString, Address, Process};
-use autosplitter_helpers::{MemoryWatcher, MemoryWatcherMap};
+use crate::{
+    missions::{Mission, COLLECTIBLES},
+    version::Version,
+};
+use asr::{string::ArrayWString, Address};
+use autosplitter_helpers::{
+    KeyedWatchers, MemorySource, MemoryWatcherMap, RegistryKey, WatcherRegistry,
+};
 
 pub struct Watchers {
-    pub missions: MemoryWatcherMap<u32>,
+    pub missions: KeyedWatchers<Mission, u32>,
     pub collectibles: MemoryWatcherMap<u32>,
-    pub game_state: MemoryWatcher<u32>,
-    pub mission_text: MemoryWatcher<ArrayWString<128>>,
-    pub progress_made: MemoryWatcher<u32>,
-    pub te_helipad: MemoryWatcher<u8>,
-    pub te_timer: MemoryWatcher<u32>,
+    pub values: WatcherRegistry,
+    pub game_state: RegistryKey<u32>,
+    pub mission_text: RegistryKey<ArrayWString<128>>,
+    pub progress_made: RegistryKey<u32>,
+    pub te_helipad: RegistryKey<u8>,
+    pub te_timer: RegistryKey<u32>,
 }
 
 impl Watchers {
@@ -17,10 +23,7 @@ impl Watchers {
         let off = version.offset();
         let adjusted = |raw: u64| (raw as i64 + off) as u64;
 
-        let mut missions = MemoryWatcherMap::new();
-        for &(key, _, address) in MISSIONS {
-            missions.insert(key, adjusted(address));
-        }
+        let missions = KeyedWatchers::new(|mission: Mission| adjusted(mission.address()));
 
         let mut collectibles = MemoryWatcherMap::new();
         for &(key, _, address, _) in COLLECTIBLES {
@@ -44,24 +47,28 @@ impl Watchers {
             adjusted(0x274F20)
         };
 
+        let mut values = WatcherRegistry::new();
+        let game_state = values.insert("game_state", game_state);
+        let mission_text = values.insert("mission_text", mission_text);
+        let progress_made = values.insert("progress_made", progress_made);
+        let te_helipad = values.insert("te_helipad", adjusted(0x35F6B8));
+        let te_timer = values.insert("te_timer", adjusted(0x35BA2C));
+
         Self {
             missions,
             collectibles,
-            game_state: MemoryWatcher::new(game_state),
-            mission_text: MemoryWatcher::new(mission_text),
-            progress_made: MemoryWatcher::new(progress_made),
-            te_helipad: MemoryWatcher::new(adjusted(0x35F6B8)),
-            te_timer: MemoryWatcher::new(adjusted(0x35BA2C)),
+            values,
+            game_state,
+            mission_text,
+            progress_made,
+            te_helipad,
+            te_timer,
         }
     }
 
-    pub fn update(&mut self, process: &Process, base: Address) {
-        self.missions.update_all(process, base);
-        self.collectibles.update_all(process, base);
-        self.game_state.update(process, base);
-        self.progress_made.update(process, base);
-        self.mission_text.update(process, base);
-        self.te_helipad.update(process, base);
-        self.te_timer.update(process, base);
+    pub fn update(&mut self, memory: &impl MemorySource, base: Address) {
+        self.missions.update_all(memory, base);
+        self.collectibles.update_all(memory, base);
+        self.values.update_all(memory, base);
     }
 }
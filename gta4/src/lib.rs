@@ -1,21 +1,30 @@
 #![no_std]
 extern crate alloc;
 
+pub mod watchers;
+
 #[global_allocator]
 static ALLOC: dlmalloc::GlobalDlmalloc = dlmalloc::GlobalDlmalloc;
 
-use alloc::{format, string::String, vec::Vec};
-use asr::{
-    future::next_tick,
-    settings::Gui,
-    timer::{self, TimerState},
-    watcher::Watcher,
-    Address, Process,
+use asr::{future::next_tick, settings::Gui, timer::TimerState};
+use autosplitter_helpers::{
+    attach::{attach_any, ProcessCandidate},
+    debug, log,
+    settings::{LoadDebounce, LogLevel},
+    ActionHistory, AsrTimer, LoadRemover, SplitGuard, Timer, Variables,
 };
 
+use crate::watchers::Watchers;
+
 asr::async_main!(stable);
 asr::panic_handler!();
 
+const PROCESSES: &[ProcessCandidate] = &[
+    ProcessCandidate::new("GTAIV.exe"),
+    // Under Wine the game runs inside the launcher's process.
+    ProcessCandidate::new("LaunchGTAIV.exe").with_module("GTAIV.exe"),
+];
+
 #[derive(Gui)]
 struct Settings {
     /// Start timer automatically
@@ -36,231 +45,258 @@ struct Settings {
     /// Split on flying rat kill
     #[default = true]
     flying_rats: bool,
+    /// Ignore loading flickers shorter than
+    load_debounce: LoadDebounce,
+    /// Publish game stats as timer variables
+    expose_variables: bool,
+    /// Debug log level
+    log_level: LogLevel,
 }
 
-struct MemoryAddresses {
-    loading: u64,
-    missions_passed: u64,
-    missions_attempted: u64,
-    stunts: u64,
-    most_wanted: u64,
-    flying_rats: u64,
-    white_loading_screen: u64,
-    video_editor: u64,
+/// Identifies a split that may only fire once per run, by the counter value
+/// that triggered it.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum SplitKey {
+    Mission(i32),
+    Stunt(i32),
+    FlyingRat(i32),
+    MostWanted(i32),
 }
 
-// Only Patch 4 supported for now
-const MEMORY_ADDRESSES: MemoryAddresses = MemoryAddresses {
-    loading: 0xC07A0C,
-    white_loading_screen: 0x01223EA8,
-    missions_passed: 0x00C61420,
-    missions_attempted: 0x00C61428,
-    stunts: 0x00C61464,
-    most_wanted: 0x00C615CC,
-    flying_rats: 0x00C615D0,
-    video_editor: 0xBCCDE0,
-};
+async fn main() {
+    let mut settings = Settings::register();
+    let mut watchers = Watchers::new();
+    let mut split_guard = SplitGuard::new();
+    let mut load_remover = load_remover();
+    let mut variables = Variables::new();
+    let mut history = ActionHistory::new("gta4");
 
-#[derive(Default, Copy, Clone)]
-struct GameState {
-    loading: Watcher<u32>,
-    missions_passed: Watcher<i32>,
-    missions_attempted: Watcher<i32>,
-    stunts: Watcher<i32>,
-    most_wanted: Watcher<i32>,
-    flying_rats: Watcher<i32>,
-    white_loading_screen: Watcher<u32>,
-    video_editor: Watcher<i32>,
-}
+    loop {
+        let (process, candidate) = attach_any(PROCESSES).await;
+        process
+            .until_closes(async {
+                if let Ok(base_address) = process.get_module_address(candidate.module) {
+                    loop {
+                        settings.update();
+                        log::set_level(settings.log_level.into());
+                        watchers.update(&process, base_address);
+                        if settings.expose_variables {
+                            publish_variables(&mut AsrTimer, &mut variables, &watchers);
+                        }
 
-impl GameState {
-    fn read<T: Copy + Default + bytemuck::CheckedBitPattern>(
-        process: &Process,
-        base_address: Address,
-        offsets: &[u64],
-    ) -> Result<T, asr::Error> {
-        process.read_pointer_path::<T>(base_address, asr::PointerSize::Bit32, offsets)
+                        tick(
+                            &mut history.timer(&mut AsrTimer),
+                            &watchers,
+                            &settings,
+                            &mut split_guard,
+                            &mut load_remover,
+                        );
+
+                        next_tick().await;
+                    }
+                }
+            })
+            .await;
     }
+}
 
-    fn update(&mut self, process: &Process, base_address: Address) {
-        self.loading
-            .update(Self::read::<u32>(process, base_address, &[MEMORY_ADDRESSES.loading]).ok());
-
-        self.missions_passed.update(
-            Self::read::<i32>(
-                process,
-                base_address,
-                &[MEMORY_ADDRESSES.missions_passed, 0x10],
-            )
-            .ok(),
+/// Publishes mission and collectible progress as timer variables.
+fn publish_variables(timer: &mut impl Timer, variables: &mut Variables, watchers: &Watchers) {
+    if let Some(missions) = watchers.missions_passed.pair() {
+        variables.set(timer, "Missions Passed", missions.current);
+    }
+    if let Some(missions) = watchers.missions_attempted.pair() {
+        variables.set(timer, "Missions Attempted", missions.current);
+    }
+    if let Some(flying_rats) = watchers.flying_rats.pair() {
+        variables.set(
+            timer,
+            "Flying Rats",
+            format_args!("{}/200", flying_rats.current),
         );
+    }
+    if let Some(stunts) = watchers.stunts.pair() {
+        variables.set(timer, "Stunt Jumps", format_args!("{}/50", stunts.current));
+    }
+    if let Some(most_wanted) = watchers.most_wanted.pair() {
+        variables.set(timer, "Most Wanted", most_wanted.current);
+    }
+}
 
-        self.missions_attempted.update(
-            Self::read::<i32>(
-                process,
-                base_address,
-                &[MEMORY_ADDRESSES.missions_attempted, 0x10],
-            )
-            .ok(),
-        );
+/// Pauses game time on loading screens and in the video editor.
+fn load_remover() -> LoadRemover<Watchers> {
+    let mut load_remover = LoadRemover::new();
+    load_remover.push(|watchers: &Watchers| Some(watchers.loading.pair()?.current == 0));
+    load_remover.push(|watchers: &Watchers| Some(watchers.video_editor.pair()?.current == 256));
+    load_remover
+}
 
-        self.white_loading_screen.update(
-            Self::read::<u32>(
-                process,
-                base_address,
-                &[MEMORY_ADDRESSES.white_loading_screen],
-            )
-            .ok(),
-        );
+/// Starts or resets on a new game and splits once for every mission, stunt
+/// jump, flying rat and most wanted target.
+fn tick(
+    timer: &mut impl Timer,
+    watchers: &Watchers,
+    settings: &Settings,
+    split_guard: &mut SplitGuard<SplitKey>,
+    load_remover: &mut LoadRemover<Watchers>,
+) {
+    if timer.state() == TimerState::NotRunning && !split_guard.is_empty() {
+        split_guard.clear();
+        debug!("Cleaning done splits list");
+    }
+    split_guard.sync(timer.current_split_index());
 
-        self.stunts.update(
-            Self::read::<i32>(process, base_address, &[MEMORY_ADDRESSES.stunts, 0x10]).ok(),
-        );
+    load_remover.set_debounce(settings.load_debounce.into());
+    load_remover.update(watchers, timer);
 
-        self.most_wanted.update(
-            Self::read::<i32>(process, base_address, &[MEMORY_ADDRESSES.most_wanted, 0x10]).ok(),
-        );
+    let (
+        Some(loading),
+        Some(missions_passed),
+        Some(missions_attempted),
+        Some(stunts),
+        Some(most_wanted),
+        Some(flying_rats),
+    ) = (
+        watchers.loading.pair(),
+        watchers.missions_passed.pair(),
+        watchers.missions_attempted.pair(),
+        watchers.stunts.pair(),
+        watchers.most_wanted.pair(),
+        watchers.flying_rats.pair(),
+    )
+    else {
+        return;
+    };
 
-        self.flying_rats.update(
-            Self::read::<i32>(process, base_address, &[MEMORY_ADDRESSES.flying_rats, 0x10]).ok(),
-        );
+    let start_check: bool = watchers.white_loading_screen.changed_to(&0) && loading.current == 0;
 
-        self.video_editor.update(
-            Self::read::<i32>(process, base_address, &[MEMORY_ADDRESSES.video_editor]).ok(),
-        );
+    let missions_check: bool = missions_attempted.current == 0;
+
+    if settings.reset_timer && start_check && missions_check && timer.state() == TimerState::Running
+    {
+        timer.reset_because(format_args!("new game"));
     }
-}
 
-async fn main() {
-    let mut settings = Settings::register();
-    let mut state = GameState::default();
-    let mut done_splits: Vec<String> = Vec::new();
-    let mut timer_state: TimerState = TimerState::NotRunning;
+    if settings.start_timer
+        && start_check
+        && missions_check
+        && timer.state() == TimerState::NotRunning
+    {
+        timer.start_because(format_args!("new game"));
+    }
 
-    loop {
-        if timer::state() != timer_state {
-            done_splits.clear();
-            timer_state = timer::state();
-            asr::print_message("Cleaning done splits list");
+    if timer.state() == TimerState::Running {
+        if settings.missions && watchers.missions_passed.increased_by(1) {
+            split_guard.try_split(
+                SplitKey::Mission(missions_passed.current),
+                timer,
+                format_args!("mission {}", missions_passed.current),
+            );
         }
 
-        // Seems to be a Linux/Wine quirk
-        let process = Process::wait_attach("LaunchGTAIV.exe").await;
-        process
-            .until_closes(async {
-                if let Ok(base_address) = process.get_module_address("GTAIV.exe") {
-                    loop {
-                        settings.update();
-                        state.update(&process, base_address);
-
-                        let Some(loading) = state.loading.pair.as_ref() else {
-                            next_tick().await;
-                            continue;
-                        };
-                        let Some(missions_passed) = state.missions_passed.pair.as_ref() else {
-                            next_tick().await;
-                            continue;
-                        };
-                        let Some(missions_attempted) = state.missions_attempted.pair.as_ref()
-                        else {
-                            next_tick().await;
-                            continue;
-                        };
-                        let Some(stunts) = state.stunts.pair.as_ref() else {
-                            next_tick().await;
-                            continue;
-                        };
-                        let Some(most_wanted) = state.most_wanted.pair.as_ref() else {
-                            next_tick().await;
-                            continue;
-                        };
-                        let Some(flying_rats) = state.flying_rats.pair.as_ref() else {
-                            next_tick().await;
-                            continue;
-                        };
-                        let Some(white_loading_screen) = state.white_loading_screen.pair.as_ref()
-                        else {
-                            next_tick().await;
-                            continue;
-                        };
-                        let Some(video_editor) = state.video_editor.pair.as_ref() else {
-                            next_tick().await;
-                            continue;
-                        };
-
-                        // Loading check
-                        match loading.current == 0 || video_editor.current == 256 {
-                            true => {
-                                timer::pause_game_time();
-                            }
-                            false => {
-                                timer::resume_game_time();
-                            }
-                        }
+        if settings.stunts && watchers.stunts.increased_by(1) {
+            split_guard.try_split(
+                SplitKey::Stunt(stunts.current),
+                timer,
+                format_args!("stunt jump {}", stunts.current),
+            );
+        }
 
-                        let start_check: bool = white_loading_screen.current == 0
-                            && white_loading_screen.old != 0
-                            && loading.current == 0;
+        if settings.flying_rats && watchers.flying_rats.increased_by(1) {
+            split_guard.try_split(
+                SplitKey::FlyingRat(flying_rats.current),
+                timer,
+                format_args!("flying rat {}", flying_rats.current),
+            );
+        }
 
-                        let missions_check: bool = missions_attempted.current == 0;
+        if settings.most_wanted && watchers.most_wanted.increased_by(1) {
+            split_guard.try_split(
+                SplitKey::MostWanted(most_wanted.current),
+                timer,
+                format_args!("most wanted {}", most_wanted.current),
+            );
+        }
+    }
+}
 
-                        if settings.reset_timer
-                            && start_check
-                            && missions_check
-                            && timer::state() == TimerState::Running
-                        {
-                            timer::reset();
-                        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asr::{Address, PointerSize};
+    use autosplitter_helpers::{FakeMemory, RecordingTimer, TimerAction};
 
-                        if settings.start_timer
-                            && start_check
-                            && missions_check
-                            && timer::state() == TimerState::NotRunning
-                        {
-                            timer::start();
-                        }
+    const BASE: u64 = 0x400000;
+    const STATS: u64 = 0x10000000;
+    const MISSIONS_PASSED: u64 = 0xC61420;
 
-                        if timer::state() == TimerState::Running {
-                            if settings.missions
-                                && missions_passed.current == missions_passed.old + 1
-                            {
-                                let key = format!("mission {}", missions_passed.current);
-                                if !done_splits.contains(&key) {
-                                    asr::print_message(&key);
-                                    timer::split();
-                                    done_splits.push(key);
-                                }
-                            }
-
-                            if settings.stunts && stunts.current == stunts.old + 1 {
-                                let key = format!("stunt {}", stunts.current);
-                                if !done_splits.contains(&key) {
-                                    timer::split();
-                                    done_splits.push(key);
-                                }
-                            }
-
-                            if settings.flying_rats && flying_rats.current == flying_rats.old + 1 {
-                                let key = format!("rat {}", flying_rats.current);
-                                if !done_splits.contains(&key) {
-                                    timer::split();
-                                    done_splits.push(key);
-                                }
-                            }
-
-                            if settings.most_wanted && most_wanted.current == most_wanted.old + 1 {
-                                let key = format!("most_wanted {}", most_wanted.current);
-                                if !done_splits.contains(&key) {
-                                    timer::split();
-                                    done_splits.push(key);
-                                }
-                            }
-                        }
+    /// Points the stat counter at `address` to a value of `value`.
+    fn write_stat(memory: &mut FakeMemory, address: u64, value: i32) {
+        let stat = STATS + address;
+        memory.write_pointer(BASE + address, stat, PointerSize::Bit32);
+        memory.write(stat + 0x10, value);
+    }
 
-                        next_tick().await;
-                    }
-                }
-            })
-            .await;
+    #[test]
+    fn starts_and_splits_once_per_mission_across_loads() {
+        let mut memory = FakeMemory::new();
+        let mut watchers = Watchers::new();
+        let mut split_guard = SplitGuard::new();
+        let mut load_remover = load_remover();
+        let mut timer = RecordingTimer::new();
+        let settings = Settings {
+            start_timer: true,
+            reset_timer: true,
+            missions: true,
+            stunts: true,
+            most_wanted: true,
+            flying_rats: true,
+            load_debounce: LoadDebounce::Off,
+            expose_variables: false,
+            log_level: LogLevel::Info,
+        };
+
+        for address in [MISSIONS_PASSED, 0xC61428, 0xC61464, 0xC615CC, 0xC615D0] {
+            write_stat(&mut memory, address, 0);
+        }
+        memory.write(BASE + 0xC07A0C, 1u32);
+        memory.write(BASE + 0x1223EA8, 1u32);
+        memory.write(BASE + 0xBCCDE0, 0i32);
+
+        let mut run = |memory: &FakeMemory, timer: &mut RecordingTimer| {
+            watchers.update(memory, Address::new(BASE));
+            tick(
+                timer,
+                &watchers,
+                &settings,
+                &mut split_guard,
+                &mut load_remover,
+            );
+            timer
+                .take_actions()
+                .into_iter()
+                .filter(|action| matches!(action, TimerAction::Start | TimerAction::Split))
+                .collect::<alloc::vec::Vec<_>>()
+        };
+
+        assert_eq!(run(&memory, &mut timer), []);
+        // The white loading screen of a new game goes away.
+        memory.write(BASE + 0x1223EA8, 0u32);
+        memory.write(BASE + 0xC07A0C, 0u32);
+        assert_eq!(run(&memory, &mut timer), [TimerAction::Start]);
+
+        memory.write(BASE + 0xC07A0C, 1u32);
+        write_stat(&mut memory, MISSIONS_PASSED, 1);
+        assert_eq!(run(&memory, &mut timer), [TimerAction::Split]);
+
+        // The stat pointer goes away during a load and comes back with the
+        // counter already increased, which mustn't split.
+        memory.unmap(BASE + MISSIONS_PASSED, 4);
+        assert_eq!(run(&memory, &mut timer), []);
+        write_stat(&mut memory, MISSIONS_PASSED, 2);
+        assert_eq!(run(&memory, &mut timer), []);
+
+        write_stat(&mut memory, MISSIONS_PASSED, 3);
+        assert_eq!(run(&memory, &mut timer), [TimerAction::Split]);
     }
 }
@@ -0,0 +1,77 @@
+use asr::Address;
+use autosplitter_helpers::{MemorySource, MemoryWatcher};
+
+struct MemoryAddresses {
+    loading: u64,
+    missions_passed: u64,
+    missions_attempted: u64,
+    stunts: u64,
+    most_wanted: u64,
+    flying_rats: u64,
+    white_loading_screen: u64,
+    video_editor: u64,
+}
+
+// Only Patch 4 supported for now
+const MEMORY_ADDRESSES: MemoryAddresses = MemoryAddresses {
+    loading: 0xC07A0C,
+    white_loading_screen: 0x01223EA8,
+    missions_passed: 0x00C61420,
+    missions_attempted: 0x00C61428,
+    stunts: 0x00C61464,
+    most_wanted: 0x00C615CC,
+    flying_rats: 0x00C615D0,
+    video_editor: 0xBCCDE0,
+};
+
+/// Creates a watcher for a stat counter behind a pointer.
+///
+/// The pointer can be null while the game loads. The watcher's default stale
+/// policy keeps the first value read after a load from counting as an
+/// increase.
+fn stat_watcher(address: u64) -> MemoryWatcher<i32> {
+    MemoryWatcher::new([address, 0x10])
+}
+
+pub struct Watchers {
+    pub loading: MemoryWatcher<u32>,
+    pub missions_passed: MemoryWatcher<i32>,
+    pub missions_attempted: MemoryWatcher<i32>,
+    pub stunts: MemoryWatcher<i32>,
+    pub most_wanted: MemoryWatcher<i32>,
+    pub flying_rats: MemoryWatcher<i32>,
+    pub white_loading_screen: MemoryWatcher<u32>,
+    pub video_editor: MemoryWatcher<i32>,
+}
+
+impl Watchers {
+    pub fn new() -> Self {
+        Self {
+            loading: MemoryWatcher::new(MEMORY_ADDRESSES.loading),
+            missions_passed: stat_watcher(MEMORY_ADDRESSES.missions_passed),
+            missions_attempted: stat_watcher(MEMORY_ADDRESSES.missions_attempted),
+            stunts: stat_watcher(MEMORY_ADDRESSES.stunts),
+            most_wanted: stat_watcher(MEMORY_ADDRESSES.most_wanted),
+            flying_rats: stat_watcher(MEMORY_ADDRESSES.flying_rats),
+            white_loading_screen: MemoryWatcher::new(MEMORY_ADDRESSES.white_loading_screen),
+            video_editor: MemoryWatcher::new(MEMORY_ADDRESSES.video_editor),
+        }
+    }
+
+    pub fn update(&mut self, memory: &impl MemorySource, base: Address) {
+        self.loading.update(memory, base);
+        self.missions_passed.update(memory, base);
+        self.missions_attempted.update(memory, base);
+        self.white_loading_screen.update(memory, base);
+        self.stunts.update(memory, base);
+        self.most_wanted.update(memory, base);
+        self.flying_rats.update(memory, base);
+        self.video_editor.update(memory, base);
+    }
+}
+
+impl Default for Watchers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
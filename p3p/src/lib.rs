@@ -4,52 +4,55 @@ extern crate alloc;
 #[global_allocator]
 static ALLOC: dlmalloc::GlobalDlmalloc = dlmalloc::GlobalDlmalloc;
 
-use asr::{
-    future::{next_tick, retry},
-    timer, Process,
+use asr::{future::next_tick, settings::Gui};
+use autosplitter_helpers::{
+    attach::{attach_any, ProcessCandidate},
+    log,
+    settings::LogLevel,
+    AsrTimer, LoadRemover, MemoryWatcher,
 };
 
 asr::async_main!(stable);
 asr::panic_handler!();
 
+const PROCESSES: &[ProcessCandidate] = &[
+    ProcessCandidate::new("P3P.exe"),
+    ProcessCandidate::new("p3p_sln_DT_m.exe"),
+];
+
+#[derive(Gui)]
+struct Settings {
+    /// Debug log level
+    log_level: LogLevel,
+}
+
 async fn main() {
+    let mut settings = Settings::register();
+    let mut load_remover = LoadRemover::new();
+    load_remover.push(|loading: &MemoryWatcher<u16>| Some(loading.pair()?.current == 4));
+
     loop {
-        timer::pause_game_time();
+        load_remover.detach(&mut AsrTimer);
 
-        let (process_name, process) = retry(|| {
-            ["P3P.exe", "p3p_sln_DT_m.exe"]
-                .into_iter()
-                .find_map(|name| Process::attach(name).map(|proc| (name, proc)))
-        })
-        .await;
+        let (process, candidate) = attach_any(PROCESSES).await;
 
-        timer::resume_game_time();
+        load_remover.attach(&mut AsrTimer);
 
-        let loading_address: u64 = if process_name == "P3P.exe" {
+        let loading_address: u64 = if candidate.process == "P3P.exe" {
             0x9CF134
         } else {
             0x130AF74
         };
+        let mut loading = MemoryWatcher::new(loading_address);
 
         process
             .until_closes(async {
-                if let Ok(base_address) = process.get_module_address(process_name) {
+                if let Ok(base_address) = process.get_module_address(candidate.module) {
                     loop {
-                        if let Ok(loading) = process.read_pointer_path::<u16>(
-                            base_address,
-                            asr::PointerSize::Bit32,
-                            &[loading_address],
-                        ) {
-                            match loading == 4 {
-                                true => {
-                                    timer::pause_game_time();
-                                }
-
-                                false => {
-                                    timer::resume_game_time();
-                                }
-                            }
-                        }
+                        settings.update();
+                        log::set_level(settings.log_level.into());
+                        loading.update(&process, base_address);
+                        load_remover.update(&loading, &mut AsrTimer);
                         next_tick().await;
                     }
                 }
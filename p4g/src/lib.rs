@@ -1,6 +1,12 @@
 #![no_std]
 extern crate alloc;
-use asr::{future::next_tick, timer, Process};
+use asr::{future::next_tick, settings::Gui};
+use autosplitter_helpers::{
+    attach::{attach_any, ProcessCandidate},
+    log,
+    settings::LogLevel,
+    AsrTimer, LoadRemover, MemoryWatcher,
+};
 
 #[global_allocator]
 static ALLOC: dlmalloc::GlobalDlmalloc = dlmalloc::GlobalDlmalloc;
@@ -8,34 +14,38 @@ static ALLOC: dlmalloc::GlobalDlmalloc = dlmalloc::GlobalDlmalloc;
 asr::async_main!(stable);
 asr::panic_handler!();
 
+const PROCESSES: &[ProcessCandidate] = &[ProcessCandidate::new("P4G.exe")];
+
 // only latest Steam supported for now
 const LOADING_ADDRESS: u64 = 0x51BCD12;
 
+#[derive(Gui)]
+struct Settings {
+    /// Debug log level
+    log_level: LogLevel,
+}
+
 async fn main() {
+    let mut settings = Settings::register();
+    let mut load_remover = LoadRemover::new();
+    load_remover.push(|loading: &MemoryWatcher<u16>| Some(loading.pair()?.current != 1));
+
     loop {
-        timer::pause_game_time();
+        load_remover.detach(&mut AsrTimer);
+
+        let (process, candidate) = attach_any(PROCESSES).await;
+        load_remover.attach(&mut AsrTimer);
 
-        let process = Process::wait_attach("P4G.exe").await;
-        timer::resume_game_time();
+        let mut loading = MemoryWatcher::new(LOADING_ADDRESS);
 
         process
             .until_closes(async {
-                if let Ok(base_address) = process.get_module_address("P4G.exe") {
+                if let Ok(base_address) = process.get_module_address(candidate.module) {
                     loop {
-                        if let Ok(loading) = process.read_pointer_path::<u16>(
-                            base_address,
-                            asr::PointerSize::Bit32,
-                            &[LOADING_ADDRESS],
-                        ) {
-                            match loading != 1 {
-                                true => {
-                                    timer::pause_game_time();
-                                }
-                                false => {
-                                    timer::resume_game_time();
-                                }
-                            }
-                        }
+                        settings.update();
+                        log::set_level(settings.log_level.into());
+                        loading.update(&process, base_address);
+                        load_remover.update(&loading, &mut AsrTimer);
                         next_tick().await;
                     }
                 }
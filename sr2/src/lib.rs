@@ -10,16 +10,26 @@ static ALLOC: dlmalloc::GlobalDlmalloc = dlmalloc::GlobalDlmalloc;
 use asr::{
     future::next_tick,
     settings::{gui::Title, Gui},
-    timer::{self, TimerState},
-    Process,
+    timer::TimerState,
+};
+use autosplitter_helpers::{
+    attach::{attach_any, detect_version, ProcessCandidate},
+    log,
+    rules::{Frequency, Predicate, Rule, RuleSet, SettingSource},
+    settings::{LoadDebounce, LogLevel},
+    trace::TraceRecorder,
+    ActionHistory, AsrTimer, LoadRemover, SplitGuard, Timer, Variables, WatcherKey,
 };
 
-use crate::{version::Version, watchers::Watchers};
+use crate::{
+    version::VERSIONS,
+    watchers::{Counter, Watchers},
+};
 
 asr::async_main!(stable);
 asr::panic_handler!();
 
-const PROCESS_NAME: &str = "SR2_pc.exe";
+const PROCESSES: &[ProcessCandidate] = &[ProcessCandidate::new("SR2_pc.exe")];
 
 #[derive(Gui)]
 struct Settings {
@@ -31,6 +41,10 @@ struct Settings {
     /// Reset timer automatically
     #[default = true]
     timer_reset: bool,
+    /// Ignore loading flickers shorter than
+    load_debounce: LoadDebounce,
+    /// Publish game stats as timer variables
+    expose_variables: bool,
     /// Main
     _main: Title,
     /// Missions
@@ -98,109 +112,401 @@ struct Settings {
     jumps: bool,
     /// Barnstorming
     barnstorming: bool,
+    /// Debug
+    _debug: Title,
+    /// Record memory trace to the log
+    trace: bool,
+    /// Debug log level
+    log_level: LogLevel,
+}
+
+/// Implements [`SettingSource`] for a settings struct, looking up its boolean
+/// fields by name, which is also their settings key.
+macro_rules! bool_settings {
+    ($settings:ty { $($field:ident),* $(,)? }) => {
+        impl SettingSource for $settings {
+            fn get_bool(&self, key: &str) -> Option<bool> {
+                match key {
+                    $(stringify!($field) => Some(self.$field),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+// The counter rules name these settings by their counter's name.
+bool_settings!(Settings {
+    missions,
+    strongholds,
+    chop_shop,
+    crowd_control,
+    derby,
+    escort,
+    fight_club,
+    fuzz,
+    heli_assault,
+    hitman,
+    fraud,
+    mayhem,
+    races,
+    septic,
+    snatch,
+    trafficking,
+    trail_blazing,
+    tags,
+    cd,
+    jumps,
+    barnstorming,
+});
+
+/// Identifies a split that may only fire once per run and isn't covered by the
+/// counter rules.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum SplitKey {
+    Hundo,
 }
 
 async fn main() {
     let mut settings = Settings::register();
+    let mut history = ActionHistory::new("sr2");
 
     loop {
-        let process = Process::wait_attach(PROCESS_NAME).await;
+        let (process, candidate) = attach_any(PROCESSES).await;
         process
             .until_closes(async {
-                if let Ok(base_address) = process.get_module_address(PROCESS_NAME) {
-                    if let Some(version) = Version::detect(&process, base_address) {
-                        let mut watchers = Watchers::new(version);
-
-                        loop {
-                            settings.update();
-                            watchers.update(&process, base_address);
-                            let timer_state = timer::state();
-
-                            if let (Some(save_load), Some(cutscene_load)) =
-                                (watchers.save_load.pair(), watchers.cutscene_load.pair())
-                            {
-                                if cutscene_load.current == 0 || save_load.current == 0 {
-                                    timer::pause_game_time();
-                                } else {
-                                    timer::resume_game_time();
-                                }
-                            }
-
-                            if timer_state == TimerState::NotRunning && settings.timer_start {
-                                let current_cutscene = watchers.current_cutscene();
-
-                                if let Some(start_flag) = watchers.start_flag.pair() {
-                                    if current_cutscene == "TSSP01-01.cscx"
-                                        && start_flag.current == 1
-                                        && start_flag.old != start_flag.current
-                                    {
-                                        timer::start();
-                                    }
-                                }
-                            }
-
-                            if timer_state == TimerState::Running {
-                                if settings.timer_reset {
-                                    if let Some(cutscene) = watchers.cutscene.pair() {
-                                        if cutscene.changed() {
-                                            if let Ok(current_cutscene) =
-                                                cutscene.current.validate_utf8()
-                                            {
-                                                if current_cutscene == "TSSP-INTRO2.cscx" {
-                                                    timer::reset();
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-
-                                if settings.hundo {
-                                    if let Some(progress_percent) = watchers.progress_percent.pair()
-                                    {
-                                        if progress_percent.current == 100
-                                            && progress_percent.changed()
-                                        {
-                                            timer::split();
-                                        }
-                                    }
-                                }
-
-                                let counter_settings = [
-                                    (settings.missions, "missions"),
-                                    (settings.strongholds, "strongholds"),
-                                    (settings.tags, "tags"),
-                                    (settings.cd, "cd"),
-                                    (settings.jumps, "jumps"),
-                                    (settings.barnstorming, "barnstorming"),
-                                    (settings.chop_shop, "chop_shop"),
-                                    (settings.crowd_control, "crowd_control"),
-                                    (settings.derby, "derby"),
-                                    (settings.escort, "escort"),
-                                    (settings.fight_club, "fight_club"),
-                                    (settings.fuzz, "fuzz"),
-                                    (settings.heli_assault, "heli_assault"),
-                                    (settings.hitman, "hitman"),
-                                    (settings.fraud, "fraud"),
-                                    (settings.mayhem, "mayhem"),
-                                    (settings.races, "races"),
-                                    (settings.septic, "septic"),
-                                    (settings.snatch, "snatch"),
-                                    (settings.trafficking, "trafficking"),
-                                    (settings.trail_blazing, "trail_blazing"),
-                                ];
-
-                                for (enabled, name) in counter_settings {
-                                    if enabled && watchers.counters[name].increased_by(1) {
-                                        timer::split();
-                                    }
-                                }
-                            }
-
-                            next_tick().await;
-                        }
+                let Some((base_address, version)) =
+                    detect_version(&process, candidate.module, VERSIONS, &mut AsrTimer).await
+                else {
+                    loop {
+                        next_tick().await;
                     }
+                };
+
+                let mut watchers = Watchers::new(version);
+                let mut recorder = TraceRecorder::new();
+                let mut split_guard = SplitGuard::new();
+                let mut counter_rules = counter_rules();
+                let mut load_remover = load_remover();
+                let mut variables = Variables::new();
+
+                loop {
+                    settings.update();
+                    log::set_level(settings.log_level.into());
+                    watchers.update(&process, base_address);
+                    if settings.expose_variables {
+                        publish_variables(&mut AsrTimer, &mut variables, &watchers);
+                    }
+                    if settings.trace {
+                        watchers.record(&mut recorder);
+                        recorder.finish_tick(asr::print_message);
+                    }
+                    tick(
+                        &mut history.timer(&mut AsrTimer),
+                        &watchers,
+                        &settings,
+                        &mut split_guard,
+                        &mut counter_rules,
+                        &mut load_remover,
+                    );
+
+                    next_tick().await;
                 }
             })
             .await;
     }
 }
+
+/// Splits whenever a counter goes up by one, once for every value it reaches.
+fn counter_rules() -> RuleSet<u32, Counter> {
+    let mut rules = RuleSet::new().with_name("counter");
+    for &counter in Counter::ALL {
+        rules.push(Rule {
+            watcher: counter,
+            predicate: Predicate::IncreasedBy(1),
+            setting: counter.name().into(),
+            default: false,
+            frequency: Frequency::EachValue,
+        });
+    }
+    rules
+}
+
+/// Publishes the completion percentage and every counter as timer variables.
+fn publish_variables(timer: &mut impl Timer, variables: &mut Variables, watchers: &Watchers) {
+    if let Some(progress) = watchers.progress_percent.pair() {
+        variables.set(timer, "Completion", format_args!("{}%", progress.current));
+    }
+    for &counter in Counter::ALL {
+        if let Some(count) = watchers.counters[counter].pair() {
+            variables.set(timer, counter.title(), count.current);
+        }
+    }
+}
+
+/// Pauses game time while a save or a cutscene is loading.
+fn load_remover() -> LoadRemover<Watchers> {
+    let mut load_remover = LoadRemover::new();
+    load_remover.push(|watchers: &Watchers| Some(watchers.cutscene_load.pair()?.current == 0));
+    load_remover.push(|watchers: &Watchers| Some(watchers.save_load.pair()?.current == 0));
+    load_remover
+}
+
+/// Starts on the opening cutscene, resets on the intro cutscene and splits
+/// at 100% and whenever a counter goes up.
+fn tick(
+    timer: &mut impl Timer,
+    watchers: &Watchers,
+    settings: &Settings,
+    split_guard: &mut SplitGuard<SplitKey>,
+    counter_rules: &mut RuleSet<u32, Counter>,
+    load_remover: &mut LoadRemover<Watchers>,
+) {
+    let timer_state = timer.state();
+
+    if timer_state == TimerState::NotRunning {
+        split_guard.clear();
+        counter_rules.clear();
+    }
+    split_guard.sync(timer.current_split_index());
+    counter_rules.sync(timer.current_split_index());
+
+    load_remover.set_debounce(settings.load_debounce.into());
+    load_remover.update(watchers, timer);
+
+    if timer_state == TimerState::NotRunning && settings.timer_start {
+        let current_cutscene = watchers.current_cutscene();
+
+        if current_cutscene == "TSSP01-01.cscx" && watchers.start_flag.changed_to(&1) {
+            timer.start_because(format_args!("cutscene {current_cutscene}"));
+        }
+    }
+
+    if timer_state != TimerState::Running {
+        return;
+    }
+
+    if settings.timer_reset && watchers.cutscene.changed_to_str("TSSP-INTRO2.cscx") {
+        timer.reset_because(format_args!("cutscene TSSP-INTRO2.cscx"));
+    }
+
+    if settings.hundo && watchers.progress_percent.changed_to(&100) {
+        split_guard.try_split(SplitKey::Hundo, timer, format_args!("100% completion"));
+    }
+
+    counter_rules.evaluate(&watchers.counters, settings, timer);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{string::String, vec::Vec};
+    use asr::{Address, PointerSize};
+    use autosplitter_helpers::{trace::TraceReplay, FakeMemory, RecordingTimer, TimerAction};
+
+    use crate::version::Version;
+
+    const BASE: u64 = 0x400000;
+    const CUTSCENE_INFO: u64 = 0x10000000;
+    const CUTSCENE: u64 = 0x10001000;
+    const START_FLAG: u64 = 0x1F870C0;
+    const PROGRESS: u64 = 0x1052C58;
+
+    fn settings() -> Settings {
+        Settings {
+            _timer: Title,
+            timer_start: true,
+            timer_reset: true,
+            load_debounce: LoadDebounce::Off,
+            expose_variables: false,
+            _main: Title,
+            missions: true,
+            strongholds: true,
+            hundo: true,
+            _activities: Title,
+            chop_shop: true,
+            crowd_control: true,
+            derby: true,
+            escort: true,
+            fight_club: true,
+            fuzz: true,
+            heli_assault: true,
+            hitman: true,
+            fraud: true,
+            mayhem: true,
+            races: true,
+            septic: true,
+            snatch: true,
+            trafficking: true,
+            trail_blazing: true,
+            _collectibles: Title,
+            tags: false,
+            cd: false,
+            jumps: false,
+            barnstorming: false,
+            _debug: Title,
+            trace: false,
+            log_level: LogLevel::Info,
+        }
+    }
+
+    /// Writes the memory of a new game, right before the run starts.
+    fn write_new_game(memory: &mut FakeMemory) {
+        let mut cutscene = [0; 255];
+        cutscene[..14].copy_from_slice(b"TSSP01-01.cscx");
+        memory.write_pointer(BASE + 0x02127D10, CUTSCENE_INFO, PointerSize::Bit32);
+        memory.write_pointer(CUTSCENE_INFO + 0x4, CUTSCENE, PointerSize::Bit32);
+        memory.write_bytes(CUTSCENE, &cutscene);
+        memory.write(BASE + START_FLAG, 0u32);
+        memory.write(BASE + PROGRESS, 99u32);
+        memory.write(BASE + 0xA9D670, 1u32);
+        memory.write(BASE + 0xA8EB88, 1u8);
+        memory.write(BASE + Counter::FightClub.address(), 0u32);
+    }
+
+    /// Runs a tick after every update of the watchers, until `update` returns
+    /// `false`, and returns the timer's starts and splits.
+    fn run_ticks(mut update: impl FnMut(&mut Watchers) -> bool) -> Vec<TimerAction> {
+        let settings = settings();
+        let mut watchers = Watchers::new(Version::Steam);
+        let mut split_guard = SplitGuard::new();
+        let mut counter_rules = counter_rules();
+        let mut load_remover = load_remover();
+        let mut timer = RecordingTimer::new();
+        let mut actions = Vec::new();
+
+        while update(&mut watchers) {
+            tick(
+                &mut timer,
+                &watchers,
+                &settings,
+                &mut split_guard,
+                &mut counter_rules,
+                &mut load_remover,
+            );
+            actions.extend(
+                timer
+                    .take_actions()
+                    .into_iter()
+                    .filter(|action| matches!(action, TimerAction::Start | TimerAction::Split)),
+            );
+        }
+        actions
+    }
+
+    #[test]
+    fn replays_a_recorded_run_with_the_same_splits() {
+        let fight_club = Counter::FightClub.address();
+        // The values written before each tick, where `None` unmaps them.
+        let script: &[&[(u64, Option<u32>)]] = &[
+            &[],
+            &[(START_FLAG, Some(1))],
+            &[(fight_club, Some(1))],
+            &[],
+            &[(PROGRESS, Some(100)), (fight_club, Some(2))],
+            &[(fight_club, None)],
+            &[(fight_club, Some(3))],
+        ];
+
+        let mut memory = FakeMemory::new();
+        write_new_game(&mut memory);
+        let mut recorder = TraceRecorder::new();
+        let mut trace = String::new();
+        let mut ticks = script.iter();
+        let live = run_ticks(|watchers| {
+            let Some(writes) = ticks.next() else {
+                return false;
+            };
+            for &(offset, value) in *writes {
+                match value {
+                    Some(value) => memory.write(BASE + offset, value),
+                    None => memory.unmap(BASE + offset, 4),
+                }
+            }
+            watchers.update(&memory, Address::new(BASE));
+            watchers.record(&mut recorder);
+            recorder.finish_tick(|line| {
+                trace.push_str(line);
+                trace.push('\n');
+            });
+            true
+        });
+
+        // The counter change across the read gap doesn't split.
+        assert_eq!(
+            live,
+            [
+                TimerAction::Start,
+                TimerAction::Split,
+                TimerAction::Split,
+                TimerAction::Split
+            ]
+        );
+
+        let mut replay = TraceReplay::parse(&trace).unwrap();
+        let replayed = run_ticks(|watchers| {
+            let advanced = replay.advance();
+            if advanced {
+                watchers.replay(&replay);
+            }
+            advanced
+        });
+        assert_eq!(replayed, live);
+    }
+
+    #[test]
+    fn every_counter_rule_has_a_setting() {
+        let settings = settings();
+        for &counter in Counter::ALL {
+            assert!(settings.get_bool(counter.name()).is_some(), "{counter:?}");
+        }
+        assert_eq!(settings.get_bool("tags"), Some(false));
+    }
+
+    #[test]
+    fn splits_for_hundo_and_a_counter_in_the_same_tick() {
+        let mut memory = FakeMemory::new();
+        let mut watchers = Watchers::new(Version::Steam);
+        let mut split_guard = SplitGuard::new();
+        let mut counter_rules = counter_rules();
+        let mut load_remover = load_remover();
+        let mut timer = RecordingTimer::new();
+        let settings = settings();
+
+        write_new_game(&mut memory);
+
+        let mut run = |memory: &FakeMemory, timer: &mut RecordingTimer| {
+            watchers.update(memory, Address::new(BASE));
+            tick(
+                timer,
+                &watchers,
+                &settings,
+                &mut split_guard,
+                &mut counter_rules,
+                &mut load_remover,
+            );
+            timer
+                .take_actions()
+                .into_iter()
+                .filter(|action| matches!(action, TimerAction::Start | TimerAction::Split))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(run(&memory, &mut timer), []);
+        memory.write(BASE + START_FLAG, 1u32);
+        assert_eq!(run(&memory, &mut timer), [TimerAction::Start]);
+
+        memory.write(BASE + PROGRESS, 100u32);
+        memory.write(BASE + Counter::FightClub.address(), 1u32);
+        assert_eq!(
+            run(&memory, &mut timer),
+            [TimerAction::Split, TimerAction::Split]
+        );
+        assert_eq!(run(&memory, &mut timer), []);
+
+        // Undoing the counter's split keeps the 100% split done.
+        timer.undo_split();
+        run(&memory, &mut timer);
+        assert!(split_guard.is_done(&SplitKey::Hundo));
+    }
+}
@@ -1,30 +1,14 @@
-use asr::{file_format::pe::read_size_of_image, Address, Process};
+use autosplitter_helpers::version::Probe;
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Version {
     GOG,
     Steam,
     SteamJuiced,
 }
 
-impl Version {
-    pub fn detect(process: &Process, base_address: Address) -> Option<Self> {
-        if let Some(module_size) = read_size_of_image(process, base_address) {
-            if module_size == 0x31AD000 {
-                return Some(Version::Steam);
-            }
-
-            if module_size == 0x31582FC {
-                return Some(Version::SteamJuiced);
-            }
-
-            if module_size == 0x3159000 {
-                return Some(Version::GOG);
-            }
-
-            None
-        } else {
-            None
-        }
-    }
-}
+pub const VERSIONS: &[(Version, &[Probe])] = &[
+    (Version::Steam, &[Probe::ImageSize(0x31AD000)]),
+    (Version::SteamJuiced, &[Probe::ImageSize(0x31582FC)]),
+    (Version::GOG, &[Probe::ImageSize(0x3159000)]),
+];
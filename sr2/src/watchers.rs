@@ -1,15 +1,103 @@
-use asr::{string::ArrayCString, Address, Process};
-use autosplitter_helpers::{MemoryWatcher, MemoryWatcherMap};
+use asr::{string::ArrayCString, Address};
+use autosplitter_helpers::{
+    trace::{TraceRecorder, TraceReplay},
+    watcher_keys, DeepPointer, KeyedWatchers, MemorySource, MemoryWatcher,
+};
 
 use crate::version::Version;
 
+watcher_keys! {
+    /// Progress counters that split when they go up by one.
+    pub enum Counter {
+        Missions => "missions",
+        Strongholds => "strongholds",
+        Tags => "tags",
+        Cd => "cd",
+        Jumps => "jumps",
+        Barnstorming => "barnstorming",
+        ChopShop => "chop_shop",
+        CrowdControl => "crowd_control",
+        Derby => "derby",
+        Escort => "escort",
+        FightClub => "fight_club",
+        Fuzz => "fuzz",
+        HeliAssault => "heli_assault",
+        Hitman => "hitman",
+        Fraud => "fraud",
+        Mayhem => "mayhem",
+        Races => "races",
+        Septic => "septic",
+        Snatch => "snatch",
+        Trafficking => "trafficking",
+        TrailBlazing => "trail_blazing",
+    }
+}
+
+impl Counter {
+    /// Returns the address of the counter.
+    pub fn address(self) -> u64 {
+        match self {
+            Counter::Missions => 0x1053384,
+            Counter::Strongholds => 0x10533C8,
+            Counter::Tags => 0x10535E8,
+            Counter::Cd => 0x27C7150,
+            Counter::Jumps => 0x10535A4,
+            Counter::Barnstorming => 0x1053670,
+            Counter::ChopShop => 0x10536B4,
+            Counter::CrowdControl => 0x10537C4,
+            Counter::Derby => 0x1053890,
+            Counter::Escort => 0x10539A0,
+            Counter::FightClub => 0x1053A28,
+            Counter::Fuzz => 0x1053AB0,
+            Counter::HeliAssault => 0x1053B38,
+            Counter::Hitman => 0x10536F8,
+            Counter::Fraud => 0x1053D14,
+            Counter::Mayhem => 0x1053E68,
+            Counter::Races => 0x1055760,
+            Counter::Septic => 0x1053F34,
+            Counter::Snatch => 0x1054000,
+            Counter::Trafficking => 0x1053918,
+            Counter::TrailBlazing => 0x1053C04,
+        }
+    }
+
+    /// Returns the name shown for the counter.
+    pub fn title(self) -> &'static str {
+        match self {
+            Counter::Missions => "Missions",
+            Counter::Strongholds => "Strongholds",
+            Counter::Tags => "Tags",
+            Counter::Cd => "CDs",
+            Counter::Jumps => "Stunt Jumps",
+            Counter::Barnstorming => "Barnstorming",
+            Counter::ChopShop => "Chop Shop",
+            Counter::CrowdControl => "Crowd Control",
+            Counter::Derby => "Destruction Derby",
+            Counter::Escort => "Escort",
+            Counter::FightClub => "Fight Club",
+            Counter::Fuzz => "FUZZ",
+            Counter::HeliAssault => "Heli Assault",
+            Counter::Hitman => "Hitman",
+            Counter::Fraud => "Insurance Fraud",
+            Counter::Mayhem => "Mayhem",
+            Counter::Races => "Races",
+            Counter::Septic => "Septic Avenger",
+            Counter::Snatch => "Snatch",
+            Counter::Trafficking => "Trafficking",
+            Counter::TrailBlazing => "Trail Blazing",
+        }
+    }
+}
+
 pub struct Watchers {
     pub start_flag: MemoryWatcher<u32>,
     pub progress_percent: MemoryWatcher<u32>,
     pub cutscene_load: MemoryWatcher<u32>,
     pub cutscene: MemoryWatcher<ArrayCString<255>>,
     pub save_load: MemoryWatcher<u8>,
-    pub counters: MemoryWatcherMap<u32>,
+    pub counters: KeyedWatchers<Counter, u32>,
+    /// The structure holding the name of the current cutscene.
+    cutscene_info: DeepPointer,
     last_cutscene: ArrayCString<255>,
 }
 
@@ -21,52 +109,56 @@ impl Watchers {
             0x1F870C0
         };
 
-        let mut counters = MemoryWatcherMap::new();
-        counters.insert("missions", 0x1053384);
-        counters.insert("strongholds", 0x10533C8);
-        counters.insert("tags", 0x10535E8);
-        counters.insert("cd", 0x27C7150);
-        counters.insert("jumps", 0x10535A4);
-        counters.insert("barnstorming", 0x1053670);
-        counters.insert("chop_shop", 0x10536B4);
-        counters.insert("crowd_control", 0x10537C4);
-        counters.insert("derby", 0x1053890);
-        counters.insert("escort", 0x10539A0);
-        counters.insert("fight_club", 0x1053A28);
-        counters.insert("fuzz", 0x1053AB0);
-        counters.insert("heli_assault", 0x1053B38);
-        counters.insert("hitman", 0x10536F8);
-        counters.insert("fraud", 0x1053D14);
-        counters.insert("mayhem", 0x1053E68);
-        counters.insert("races", 0x1055760);
-        counters.insert("septic", 0x1053F34);
-        counters.insert("snatch", 0x1054000);
-        counters.insert("trafficking", 0x1053918);
-        counters.insert("trail_blazing", 0x1053C04);
-
         Self {
             start_flag: MemoryWatcher::new(start_flag),
             progress_percent: MemoryWatcher::new(0x1052C58),
             cutscene_load: MemoryWatcher::new(0xA9D670),
-            cutscene: MemoryWatcher::new([0x02127D10, 0x4, 0x0]),
+            cutscene: MemoryWatcher::new(0x0),
             save_load: MemoryWatcher::new(0xA8EB88),
-            counters,
+            counters: KeyedWatchers::new(Counter::address),
+            cutscene_info: DeepPointer::new([0x02127D10, 0x4]),
             last_cutscene: ArrayCString::new(),
         }
     }
 
-    pub fn update(&mut self, process: &Process, base: Address) {
-        self.start_flag.update(process, base);
-        self.progress_percent.update(process, base);
-        self.cutscene_load.update(process, base);
-        self.cutscene.update(process, base);
+    pub fn update(&mut self, memory: &impl MemorySource, base: Address) {
+        self.start_flag.update(memory, base);
+        self.progress_percent.update(memory, base);
+        self.cutscene_load.update(memory, base);
+        self.cutscene_info.resolve(memory, base);
+        self.cutscene.update_from(memory, &self.cutscene_info);
+        self.update_last_cutscene();
+        self.save_load.update(memory, base);
+        self.counters.update_all(memory, base);
+    }
+
+    /// Updates the watchers from the current tick of a recorded trace.
+    pub fn replay(&mut self, replay: &TraceReplay) {
+        replay.update("start_flag", &mut self.start_flag);
+        replay.update("progress_percent", &mut self.progress_percent);
+        replay.update("cutscene_load", &mut self.cutscene_load);
+        replay.update("cutscene", &mut self.cutscene);
+        self.update_last_cutscene();
+        replay.update("save_load", &mut self.save_load);
+        replay.update_keyed("counters", &mut self.counters);
+    }
+
+    /// Records the watchers' values into a trace.
+    pub fn record(&self, recorder: &mut TraceRecorder) {
+        recorder.record("start_flag", &self.start_flag);
+        recorder.record("progress_percent", &self.progress_percent);
+        recorder.record("cutscene_load", &self.cutscene_load);
+        recorder.record("cutscene", &self.cutscene);
+        recorder.record("save_load", &self.save_load);
+        recorder.record_keyed("counters", &self.counters);
+    }
+
+    fn update_last_cutscene(&mut self) {
         if let Some(cutscene) = self.cutscene.pair() {
             if cutscene.current.validate_utf8().is_ok() {
                 self.last_cutscene = cutscene.current;
             }
         }
-        self.save_load.update(process, base);
-        self.counters.update_all(process, base);
     }
 
     pub fn current_cutscene(&self) -> &str {